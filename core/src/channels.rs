@@ -0,0 +1,83 @@
+use crate::Rgba;
+
+/// A color in every channel type supported by `#[derive(FromColorThis)]`, built by `colorthis::color!`.
+///
+/// Ints are scaled to the range of their type, `i8` and `i16` to `0..=127` and `0..=32767`,
+/// with the rounding and scale of the macros. Floats are in `0.0..=1.0`.
+///
+/// More channel types may be added, so this can only be constructed by `color!`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Channels {
+    pub u8: [u8; 4],
+    pub u16: [u16; 4],
+    pub i8: [i8; 4],
+    pub i16: [i16; 4],
+    pub f32: [f32; 4],
+    pub f64: [f64; 4],
+    /// Linear RGB, alpha is not affected.
+    pub linear_f32: [f32; 4],
+    /// Linear RGB, alpha is not affected.
+    pub linear_f64: [f64; 4],
+    /// RGB multiplied by alpha.
+    pub premul_u8: [u8; 4],
+    /// RGB multiplied by alpha.
+    pub premul_f32: [f32; 4],
+    /// RGB multiplied by alpha.
+    pub premul_f64: [f64; 4],
+}
+
+impl Channels {
+    /// Used by `colorthis::color!`, the arguments are the fields in order.
+    #[doc(hidden)]
+    #[allow(clippy::too_many_arguments)]
+    pub const fn __new(
+        u8: [u8; 4],
+        u16: [u16; 4],
+        i8: [i8; 4],
+        i16: [i16; 4],
+        f32: [f32; 4],
+        f64: [f64; 4],
+        linear_f32: [f32; 4],
+        linear_f64: [f64; 4],
+        premul_u8: [u8; 4],
+        premul_f32: [f32; 4],
+        premul_f64: [f64; 4],
+    ) -> Self {
+        Channels { u8, u16, i8, i16, f32, f64, linear_f32, linear_f64, premul_u8, premul_f32, premul_f64 }
+    }
+}
+
+/// A color type that `colorthis::color!` can construct.
+///
+/// `#[derive(FromColorThis)]` implements this, and an inherent `const fn from_color_this`
+/// that `color!` calls first, so `color!` can be used in a `const`.
+///
+/// ```
+/// use colorthis_core::{Channels, FromColorThis};
+///
+/// struct Gray(u8);
+///
+/// impl FromColorThis for Gray {
+///     fn from_color_this(color: Channels) -> Self {
+///         let [r, g, b, _] = color.u8;
+///         Gray(((r as u16 + g as u16 + b as u16) / 3) as u8)
+///     }
+/// }
+/// ```
+pub trait FromColorThis {
+    fn from_color_this(color: Channels) -> Self;
+}
+
+impl Rgba {
+    pub const fn from_color_this(color: Channels) -> Self {
+        let [r, g, b, a] = color.f32;
+        Rgba { r, g, b, a }
+    }
+}
+
+impl FromColorThis for Rgba {
+    fn from_color_this(color: Channels) -> Self {
+        Rgba::from_color_this(color)
+    }
+}
@@ -0,0 +1,525 @@
+use crate::{Color, Context, Result, hex, names};
+use crate::gradient::{self, HueMethod};
+use crate::space::{self, Component, Unit, Syntax};
+
+/// A component value in CSS functional notation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssToken {
+    Number(f64),
+    Percentage(f64),
+    Dimension(f64, String),
+    Ident(String),
+    /// A function like `rgb(...)`, or a parenthesised block with an empty name.
+    Function(String, Vec<CssToken>),
+    /// A hex color like `#336699`, without the `#`.
+    Hash(String),
+    /// `*`, `+` or `-` in `calc()`.
+    Delim(char),
+    Comma,
+    Slash,
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'-'
+}
+
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'-'
+}
+
+fn starts_number(s: &[u8]) -> bool {
+    matches!(s,
+        [b'0'..=b'9', ..]
+        | [b'.', b'0'..=b'9', ..]
+        | [b'+' | b'-', b'0'..=b'9', ..]
+        | [b'+' | b'-', b'.', b'0'..=b'9', ..]
+    )
+}
+
+struct Lexer<'t> {
+    src: &'t [u8],
+    pos: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        // Only ascii is ever consumed here.
+        std::str::from_utf8(&self.src[start..self.pos]).unwrap()
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        self.take_while(|c| c.is_ascii_digit());
+        if self.peek() == Some(b'.') && self.src.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+            self.take_while(|c| c.is_ascii_digit());
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            match &self.src[self.pos + 1..] {
+                [b'0'..=b'9', ..] => self.pos += 1,
+                [b'+' | b'-', b'0'..=b'9', ..] => self.pos += 2,
+                _ => return self.finish_number(start),
+            }
+            self.take_while(|c| c.is_ascii_digit());
+        }
+        self.finish_number(start)
+    }
+
+    fn finish_number(&self, start: usize) -> Result<f64> {
+        let num = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+        match num.parse() {
+            Ok(f) => Ok(f),
+            Err(_) => bail!("Invalid number {} in CSS color.", num),
+        }
+    }
+
+    /// Reads tokens until end of input, or until the `)` closing the current function.
+    fn tokens(&mut self, nested: bool) -> Result<Vec<CssToken>> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = &self.src[self.pos..];
+            match self.peek() {
+                None if nested => bail!("Unclosed parenthesis in CSS color."),
+                None => return Ok(tokens),
+                Some(b')') if nested => {
+                    self.pos += 1;
+                    return Ok(tokens);
+                },
+                Some(b',') => {
+                    self.pos += 1;
+                    tokens.push(CssToken::Comma);
+                },
+                Some(b'/') => {
+                    self.pos += 1;
+                    tokens.push(CssToken::Slash);
+                },
+                Some(b'#') => {
+                    self.pos += 1;
+                    let hex = self.take_while(|c| c.is_ascii_alphanumeric()).to_owned();
+                    tokens.push(CssToken::Hash(hex));
+                },
+                Some(b'(') => {
+                    self.pos += 1;
+                    let args = self.tokens(true)?;
+                    tokens.push(CssToken::Function(String::new(), args));
+                },
+                Some(c @ (b'*' | b'+')) if !starts_number(rest) => {
+                    self.pos += 1;
+                    tokens.push(CssToken::Delim(c as char));
+                },
+                Some(b'-') if !starts_number(rest) && !rest.get(1).is_some_and(|&c| is_ident(c)) => {
+                    self.pos += 1;
+                    tokens.push(CssToken::Delim('-'));
+                },
+                Some(_) if starts_number(rest) => {
+                    let num = self.number()?;
+                    if self.peek() == Some(b'%') {
+                        self.pos += 1;
+                        tokens.push(CssToken::Percentage(num));
+                    } else if self.peek().is_some_and(is_ident_start) {
+                        let unit = self.take_while(is_ident).to_ascii_lowercase();
+                        tokens.push(CssToken::Dimension(num, unit));
+                    } else {
+                        tokens.push(CssToken::Number(num));
+                    }
+                },
+                Some(c) if is_ident_start(c) => {
+                    let ident = self.take_while(is_ident).to_ascii_lowercase();
+                    if self.peek() == Some(b'(') {
+                        self.pos += 1;
+                        let args = self.tokens(true)?;
+                        tokens.push(CssToken::Function(ident, args));
+                    } else {
+                        tokens.push(CssToken::Ident(ident));
+                    }
+                },
+                Some(c) => bail!("Unexpected character {:?} in CSS color.", c as char),
+            }
+        }
+    }
+}
+
+/// Tokenize a CSS color string.
+pub fn tokenize(s: &str) -> Result<Vec<CssToken>> {
+    Lexer { src: s.as_bytes(), pos: 0 }.tokens(false)
+}
+
+/// Arguments of a color function, split into channels and an optional alpha.
+struct Arguments {
+    channels: Vec<CssToken>,
+    alpha: Option<CssToken>,
+    legacy: bool,
+}
+
+/// Split arguments of either the legacy comma syntax `(a, b, c[, alpha])`
+/// or the modern space syntax `(a b c[ / alpha])`.
+fn split_arguments(name: &str, args: Vec<CssToken>) -> Result<Arguments> {
+    let legacy = args.contains(&CssToken::Comma);
+    let mut channels = Vec::new();
+    let mut alpha = None;
+    if legacy {
+        let mut iter = args.into_iter();
+        loop {
+            match iter.next() {
+                Some(CssToken::Comma | CssToken::Slash) | None => {
+                    bail!("Invalid arguments to {}().", name)
+                },
+                Some(tt) => channels.push(tt),
+            }
+            match iter.next() {
+                Some(CssToken::Comma) => (),
+                None => break,
+                Some(_) => bail!("Expected comma in {}().", name),
+            }
+        }
+        if channels.len() == 4 {
+            alpha = channels.pop();
+        }
+        if channels.iter().chain(&alpha).any(is_none) {
+            bail!("`none` is not allowed in the legacy comma syntax of {}().", name)
+        }
+    } else {
+        let mut iter = args.into_iter();
+        for tt in iter.by_ref() {
+            match tt {
+                CssToken::Slash => {
+                    match (iter.next(), iter.next()) {
+                        (Some(CssToken::Slash), _) | (None, _) | (_, Some(_)) => {
+                            bail!("Expected a single alpha value after / in {}().", name)
+                        },
+                        (a, None) => alpha = a,
+                    }
+                    break;
+                },
+                tt => channels.push(tt),
+            }
+        }
+    }
+    if channels.len() != 3 {
+        bail!("Expected 3 channels in {}(), found {}.", name, channels.len())
+    }
+    Ok(Arguments { channels, alpha, legacy })
+}
+
+fn is_none(token: &CssToken) -> bool {
+    matches!(token, CssToken::Ident(s) if s == "none")
+}
+
+/// Parse an alpha value, either a number in `0..=1` or a percentage.
+fn parse_alpha(token: Option<&CssToken>) -> Result<Option<f64>> {
+    Ok(match token {
+        None => None,
+        Some(CssToken::Number(n)) => Some(*n),
+        Some(CssToken::Percentage(p)) => Some(*p / 100.0),
+        Some(t) if is_none(t) => Some(0.0),
+        Some(t) => bail!("Expected number or percentage as alpha, found {:?}.", t),
+    })
+}
+
+/// Parse `rgb()` and `rgba()`, channels are numbers in `0..=255` or percentages.
+fn parse_rgb(name: &str, args: Vec<CssToken>, ctx: &Context) -> Result<Color> {
+    let args = split_arguments(name, args)?;
+    if args.legacy {
+        let percent = args.channels.iter().filter(|x| matches!(x, CssToken::Percentage(_))).count();
+        if percent != 0 && percent != 3 {
+            bail!("Cannot mix numbers and percentages in the legacy comma syntax of {}().", name)
+        }
+    }
+    let mut ints = true;
+    let mut rgb = [0.0; 3];
+    for (v, token) in rgb.iter_mut().zip(&args.channels) {
+        *v = match token {
+            CssToken::Number(n) => {
+                ints &= n.fract() == 0.0;
+                *n / 255.0
+            },
+            CssToken::Percentage(p) => {
+                ints = false;
+                *p / 100.0
+            },
+            t if is_none(t) => 0.0,
+            t => bail!("Expected number or percentage in {}(), found {:?}.", name, t),
+        };
+    }
+    Ok(match parse_alpha(args.alpha.as_ref())? {
+        None if ints => {
+            let [r, g, b] = rgb.map(|x| (x * 255.0).round() as i64);
+            Color::Ints([r, g, b, ctx.alpha_int()])
+        },
+        alpha => Color::Floats([rgb[0], rgb[1], rgb[2], alpha.unwrap_or(ctx.alpha)]),
+    })
+}
+
+impl CssToken {
+    fn to_component(&self) -> Result<Component> {
+        Ok(match self {
+            CssToken::Number(n) => Component { value: *n, unit: Unit::Number },
+            CssToken::Percentage(p) => Component { value: *p, unit: Unit::Percent },
+            CssToken::Dimension(n, unit) => Component { value: *n, unit: Unit::Dimension(unit.clone()) },
+            t if is_none(t) => Component { value: 0.0, unit: Unit::None },
+            t => bail!("Expected number, percentage or angle, found {:?}.", t),
+        })
+    }
+}
+
+/// Parse color spaces like `hsl()` and `hwb()`.
+fn parse_space(name: &str, args: Vec<CssToken>, ctx: &Context) -> Result<Color> {
+    let args = split_arguments(name, args)?;
+    if args.legacy {
+        if !matches!(name, "hsl" | "hsla") {
+            bail!("{}() does not support the legacy comma syntax.", name)
+        }
+        if !args.channels[1..].iter().all(|x| matches!(x, CssToken::Percentage(_))) {
+            bail!("Saturation and lightness must be percentages in the legacy comma syntax of {}().", name)
+        }
+    }
+    let channels = args.channels.iter().map(CssToken::to_component).collect::<Result<Vec<_>>>()?;
+    let alpha = args.alpha.map(|x| x.to_component()).transpose()?;
+    space::parse_space(name, &channels, alpha.as_ref(), Syntax::Css, ctx)
+}
+
+/// Checks if `name` is a color function supported by [`parse_css`].
+pub fn is_function(name: &str) -> bool {
+    matches!(name, "rgb" | "rgba" | "color-mix") || space::is_space(name)
+}
+
+/// Parse a color function like `rgb()`, `color-mix()` or a relative color like `rgb(from red r g b)`.
+fn parse_function(name: &str, args: Vec<CssToken>, ctx: &Context) -> Result<Color> {
+    match name {
+        "color-mix" => parse_color_mix(args, ctx),
+        _ if args.first().is_some_and(|x| matches!(x, CssToken::Ident(s) if s == "from")) => {
+            parse_relative(name, args, ctx)
+        },
+        "rgb" | "rgba" => parse_rgb(name, args, ctx),
+        name if space::is_space(name) => parse_space(name, args, ctx),
+        _ => bail!("Unsupported CSS color function {}().", name),
+    }
+}
+
+/// Parse a color inside another color function, like a name, a hex color or a function.
+fn parse_origin(token: &CssToken, ctx: &Context) -> Result<[f64; 4]> {
+    let color = match token {
+        CssToken::Function(name, args) if !name.is_empty() => parse_function(name, args.clone(), ctx)?,
+        CssToken::Hash(digits) => hex::parse_hex(digits, ctx)?,
+        CssToken::Ident(name) => match names::builtin_color(name) {
+            Some(color) => Color::Ints(color.map(i64::from)),
+            None => bail!("Unknown color name {} in CSS color.", name),
+        },
+        t => bail!("Expected a color, found {:?}.", t),
+    };
+    Ok(color.to_floats())
+}
+
+/// Parse `color-mix(in space [hue-method hue], color [p%], color [p%])`.
+fn parse_color_mix(args: Vec<CssToken>, ctx: &Context) -> Result<Color> {
+    let mut parts = args.split(|x| x == &CssToken::Comma);
+    let (Some(method), Some(a), Some(b), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        bail!("Expected color-mix(in space, color, color).")
+    };
+    let (space, hue) = match method {
+        [CssToken::Ident(i), CssToken::Ident(space), rest @ ..] if i == "in" => {
+            let space = match space.as_str() {
+                "srgb" => gradient::Space::Srgb,
+                "srgb-linear" => gradient::Space::Linear,
+                "oklab" => gradient::Space::Oklab,
+                "oklch" => gradient::Space::Oklch,
+                _ => bail!("Unsupported color-mix() space {}, expected one of srgb, srgb-linear, oklab or oklch.", space),
+            };
+            let hue = match rest {
+                [] => HueMethod::Shorter,
+                [CssToken::Ident(method), CssToken::Ident(h)] if h == "hue" && space == gradient::Space::Oklch => match method.as_str() {
+                    "shorter" => HueMethod::Shorter,
+                    "longer" => HueMethod::Longer,
+                    "increasing" => HueMethod::Increasing,
+                    "decreasing" => HueMethod::Decreasing,
+                    _ => bail!("Unknown hue interpolation method {} hue.", method),
+                },
+                _ => bail!("Invalid color-mix() method, expected like `in oklch longer hue`."),
+            };
+            (space, hue)
+        },
+        _ => bail!("Expected `in space` in color-mix()."),
+    };
+    let stop = |tokens: &[CssToken]| match tokens {
+        [color] => Ok((parse_origin(color, ctx)?, None)),
+        [color, CssToken::Percentage(p)] | [CssToken::Percentage(p), color] if (0.0..=100.0).contains(p) => {
+            Ok((parse_origin(color, ctx)?, Some(*p / 100.0)))
+        },
+        _ => bail!("Expected a color and an optional percentage in color-mix()."),
+    };
+    let ((a, p1), (b, p2)) = (stop(a)?, stop(b)?);
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (0.5, 0.5),
+        (Some(p1), None) => (p1, 1.0 - p1),
+        (None, Some(p2)) => (1.0 - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let sum = p1 + p2;
+    if sum <= 0.0 {
+        bail!("Percentages in color-mix() cannot both be 0%.")
+    }
+    let [r, g, b, alpha] = gradient::interpolate(a, b, p2 / sum, space, hue);
+    Ok(Color::Floats([r, g, b, alpha * sum.min(1.0)]))
+}
+
+/// Channel keywords of a relative color, resolved against the origin color.
+fn channel_keywords(name: &str, origin: [f64; 4]) -> [(&'static str, f64); 4] {
+    let rgb = [origin[0], origin[1], origin[2]];
+    let alpha = ("alpha", origin[3]);
+    match name {
+        "rgb" | "rgba" => {
+            let [r, g, b] = rgb.map(|x| x * 255.0);
+            [("r", r), ("g", g), ("b", b), alpha]
+        },
+        "hsl" | "hsla" => {
+            let [h, s, l] = space::srgb_to_hsl(rgb);
+            [("h", h), ("s", s * 100.0), ("l", l * 100.0), alpha]
+        },
+        "hwb" => {
+            let [h, s, v] = space::srgb_to_hsv(rgb);
+            [("h", h), ("w", (1.0 - s) * v * 100.0), ("b", (1.0 - v) * 100.0), alpha]
+        },
+        "lab" => {
+            let [l, a, b] = space::srgb_to_lab(rgb);
+            [("l", l), ("a", a), ("b", b), alpha]
+        },
+        "lch" => {
+            let [l, c, h] = space::lab_to_lch(space::srgb_to_lab(rgb));
+            [("l", l), ("c", c), ("h", h), alpha]
+        },
+        "oklab" => {
+            let [l, a, b] = space::srgb_to_oklab(rgb);
+            [("l", l), ("a", a), ("b", b), alpha]
+        },
+        "oklch" => {
+            let [l, c, h] = space::srgb_to_oklch(rgb);
+            [("l", l), ("c", c), ("h", h), alpha]
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Parse relative colors like `rgb(from #336699 r g calc(b * 0.5))`,
+/// channel keywords are replaced by numbers of the origin color in the color space.
+fn parse_relative(name: &str, args: Vec<CssToken>, ctx: &Context) -> Result<Color> {
+    if !matches!(name, "rgb" | "rgba") && !space::is_space(name) {
+        bail!("Relative colors are not supported by {}().", name)
+    }
+    let Some(origin) = args.get(1) else {
+        bail!("Expected a color after `from` in {}().", name)
+    };
+    let keywords = channel_keywords(name, parse_origin(origin, ctx)?);
+    let args = args[2..].iter().map(|token| match token {
+        CssToken::Slash => Ok(CssToken::Slash),
+        token => resolve(token, &keywords),
+    }).collect::<Result<_>>()?;
+    match name {
+        "rgb" | "rgba" => parse_rgb(name, args, ctx),
+        name => parse_space(name, args, ctx),
+    }
+}
+
+/// Replace channel keywords and evaluate `calc()`.
+fn resolve(token: &CssToken, keywords: &[(&str, f64)]) -> Result<CssToken> {
+    Ok(match token {
+        CssToken::Ident(s) if s != "none" => match keywords.iter().find(|(k, _)| k == s) {
+            Some((_, v)) => CssToken::Number(*v),
+            None => bail!("Unknown channel keyword {}, expected one of {}.", s,
+                keywords.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(", ")),
+        },
+        CssToken::Function(f, args) if f == "calc" => CssToken::Number(calc(args, keywords)?),
+        CssToken::Function(f, _) => bail!("Unsupported function {}() in a relative color.", f),
+        token => token.clone(),
+    })
+}
+
+/// Evaluate a `calc()` sum of products over numbers and channel keywords.
+fn calc(tokens: &[CssToken], keywords: &[(&str, f64)]) -> Result<f64> {
+    let value = |token: &CssToken| match token {
+        CssToken::Function(f, args) if f.is_empty() || f == "calc" => calc(args, keywords),
+        token => match resolve(token, keywords)? {
+            CssToken::Number(n) => Ok(n),
+            t => bail!("Expected a number in calc(), found {:?}.", t),
+        },
+    };
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    let mut iter = tokens.iter();
+    loop {
+        let Some(first) = iter.next() else {
+            bail!("Expected a value in calc().")
+        };
+        let mut product = value(first)?;
+        let next = loop {
+            match iter.next() {
+                Some(CssToken::Delim('*')) => product *= value(iter.next().unwrap_or(&CssToken::Comma))?,
+                Some(CssToken::Slash) => product /= value(iter.next().unwrap_or(&CssToken::Comma))?,
+                other => break other,
+            }
+        };
+        sum += sign * product;
+        sign = match next {
+            None => return Ok(sum),
+            Some(CssToken::Delim('+')) => 1.0,
+            Some(CssToken::Delim('-')) => -1.0,
+            // `b -5` is lexed as a negative number.
+            Some(t) => bail!("Expected an operator with spaces around it in calc(), found {:?}.", t),
+        };
+    }
+}
+
+/// Parse a color in CSS functional notation like `rgb(12 200 40 / 50%)`.
+pub fn parse_css(s: &str, ctx: &Context) -> Result<Color> {
+    let mut tokens = tokenize(s)?.into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(CssToken::Function(name, args)), None) if !name.is_empty() => parse_function(&name, args, ctx),
+        _ => bail!("Invalid CSS color: {}.", s),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tokenize, CssToken::*};
+
+    #[test]
+    pub fn test_tokenizer() {
+        assert_eq!(tokenize("rgb(12 -.5e1 40% / none)").unwrap(), vec![
+            Function("rgb".to_owned(), vec![
+                Number(12.0), Number(-5.0), Percentage(40.0), Slash, Ident("none".to_owned())
+            ])
+        ]);
+        assert_eq!(tokenize("RGBA(1,2,3,0.5)").unwrap(), vec![
+            Function("rgba".to_owned(), vec![
+                Number(1.0), Comma, Number(2.0), Comma, Number(3.0), Comma, Number(0.5)
+            ])
+        ]);
+        assert_eq!(tokenize("rgb(from #369 r calc((g + 1) * -2 - b) b)").unwrap(), vec![
+            Function("rgb".to_owned(), vec![
+                Ident("from".to_owned()), Hash("369".to_owned()), Ident("r".to_owned()),
+                Function("calc".to_owned(), vec![
+                    Function(String::new(), vec![Ident("g".to_owned()), Delim('+'), Number(1.0)]),
+                    Delim('*'), Number(-2.0), Delim('-'), Ident("b".to_owned()),
+                ]),
+                Ident("b".to_owned()),
+            ])
+        ]);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::space;
+
+/// Color space used for interpolation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Space {
+    Srgb,
+    Linear,
+    #[default]
+    Oklab,
+    Oklch,
+}
+
+/// How hues are interpolated in polar color spaces, like CSS `<hue-interpolation-method>`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HueMethod {
+    #[default]
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+/// Colors with a chroma below this have no hue.
+const ACHROMATIC: f64 = 1e-4;
+
+impl Space {
+    /// Convert sRGB to the coordinates of the space.
+    fn encode(self, rgb: [f64; 3]) -> [f64; 3] {
+        match self {
+            Space::Srgb => rgb,
+            Space::Linear => rgb.map(space::srgb_decode),
+            Space::Oklab => space::srgb_to_oklab(rgb),
+            Space::Oklch => space::srgb_to_oklch(rgb),
+        }
+    }
+
+    /// Convert coordinates of the space to sRGB, out of gamut colors are always gamut mapped
+    /// since interpolated colors routinely leave sRGB, even with `strict-gamut`.
+    fn decode(self, c: [f64; 3]) -> [f64; 3] {
+        match self {
+            Space::Srgb => c,
+            Space::Linear => space::snap(c.map(space::srgb_encode)),
+            Space::Oklab => space::gamut_map(space::lab_to_lch(c)),
+            Space::Oklch => space::gamut_map(c),
+        }
+    }
+}
+
+/// Adjust the second hue for the hue interpolation method.
+fn fix_hue(a: f64, b: f64, method: HueMethod) -> f64 {
+    let d = b - a;
+    match method {
+        HueMethod::Shorter if d > 180.0 => b - 360.0,
+        HueMethod::Shorter if d < -180.0 => b + 360.0,
+        HueMethod::Longer if (0.0..180.0).contains(&d) && d != 0.0 => b - 360.0,
+        HueMethod::Longer if (-180.0..=0.0).contains(&d) && d != 0.0 => b + 360.0,
+        HueMethod::Increasing if d < 0.0 => b + 360.0,
+        HueMethod::Decreasing if d > 0.0 => b - 360.0,
+        _ => b,
+    }
+}
+
+/// Interpolate between two sRGB colors with premultiplied alpha, `t` in `0.0..=1.0`.
+pub fn interpolate(a: [f64; 4], b: [f64; 4], t: f64, space: Space, hue: HueMethod) -> [f64; 4] {
+    let [x, y] = [a, b].map(|c| space.encode([c[0], c[1], c[2]]));
+    let [mut x, mut y] = [x, y];
+    if space == Space::Oklch {
+        // Achromatic colors take the hue of the other color.
+        if x[1] < ACHROMATIC {
+            x[2] = y[2];
+        }
+        if y[1] < ACHROMATIC {
+            y[2] = x[2];
+        }
+        y[2] = fix_hue(x[2], y[2], hue);
+    }
+    let alpha = a[3] + (b[3] - a[3]) * t;
+    let polar = |i: usize| space == Space::Oklch && i == 2;
+    let mut c = [0.0; 3];
+    for i in 0..3 {
+        c[i] = if polar(i) {
+            (x[i] + (y[i] - x[i]) * t).rem_euclid(360.0)
+        } else if alpha > 0.0 {
+            (x[i] * a[3] + (y[i] * b[3] - x[i] * a[3]) * t) / alpha
+        } else {
+            x[i] + (y[i] - x[i]) * t
+        };
+    }
+    let [r, g, b] = space.decode(c);
+    [r, g, b, alpha]
+}
+
+/// Positions of stops, missing positions are spread evenly between their neighbours,
+/// and positions smaller than a previous one are raised to it, like CSS gradients.
+pub fn positions(stops: &[Option<f64>]) -> Vec<f64> {
+    let mut result: Vec<Option<f64>> = stops.to_vec();
+    let last = result.len() - 1;
+    result[0] = result[0].or(Some(0.0));
+    result[last] = result[last].or(Some(1.0));
+    let mut max = 0.0f64;
+    for x in result.iter_mut().flatten() {
+        max = max.max(*x);
+        *x = max;
+    }
+    let mut start = 0;
+    for i in 1..result.len() {
+        if let Some(end) = result[i] {
+            let from = result[start].unwrap();
+            for (j, x) in result.iter_mut().enumerate().take(i).skip(start + 1) {
+                *x = Some(from + (end - from) * (j - start) as f64 / (i - start) as f64);
+            }
+            start = i;
+        }
+    }
+    result.into_iter().map(Option::unwrap).collect()
+}
+
+/// Sample `steps` colors evenly from the first to the last position.
+pub fn gradient(stops: &[(f64, [f64; 4])], steps: usize, space: Space, hue: HueMethod) -> Vec<[f64; 4]> {
+    (0..steps).map(|i| {
+        let t = if steps == 1 { 0.0 } else { i as f64 / (steps - 1) as f64 };
+        let t = stops[0].0 + (stops[stops.len() - 1].0 - stops[0].0) * t;
+        let index = stops.iter().rposition(|(p, _)| *p <= t).unwrap_or(0).min(stops.len() - 2);
+        let ((p0, a), (p1, b)) = (stops[index], stops[index + 1]);
+        if p1 <= p0 {
+            return b
+        }
+        interpolate(a, b, ((t - p0) / (p1 - p0)).clamp(0.0, 1.0), space, hue)
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_positions() {
+        assert_eq!(positions(&[None, None, None]), [0.0, 0.5, 1.0]);
+        assert_eq!(positions(&[None, Some(0.25), None, None]), [0.0, 0.25, 0.625, 1.0]);
+        assert_eq!(positions(&[Some(0.5), Some(0.3), None]), [0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    pub fn test_fix_hue() {
+        assert_eq!(fix_hue(10.0, 350.0, HueMethod::Shorter), -10.0);
+        assert_eq!(fix_hue(10.0, 90.0, HueMethod::Longer), -270.0);
+        assert_eq!(fix_hue(90.0, 10.0, HueMethod::Increasing), 370.0);
+        assert_eq!(fix_hue(10.0, 90.0, HueMethod::Decreasing), -270.0);
+    }
+
+    #[test]
+    pub fn test_interpolate_gamut() {
+        // The midpoint of red and blue in OKLCH is outside of sRGB.
+        let c = interpolate([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0], 0.5, Space::Oklch, HueMethod::Shorter);
+        assert!(c.iter().all(|x| (0.0..=1.0).contains(x)), "{:?}", c);
+    }
+}
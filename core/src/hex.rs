@@ -0,0 +1,86 @@
+use crate::{Color, Context, Result, ParseError};
+
+const fn digit(a: u8) -> Result<u8, &'static str> {
+    match a {
+        b'0'..=b'9' => Ok(a - b'0'),
+        b'a'..=b'f' => Ok(a - b'a' + 10),
+        b'A'..=b'F' => Ok(a - b'A' + 10),
+        _ => Err("Not a valid hexadecial number."),
+    }
+}
+
+/// Decode hex digits into channels and whether an alpha was given, usable in `const`.
+const fn decode(lit: &[u8]) -> Result<([u8; 4], bool), &'static str> {
+    let (compact, alpha) = match lit.len() {
+        3 if cfg!(feature="compact") => (true, false),
+        4 if cfg!(feature="compact") => (true, true),
+        6 => (false, false),
+        8 => (false, true),
+        _ => return Err("Invalid color syntax, must be of length 6 or 8."),
+    };
+    let mut result = [255; 4];
+    let mut i = 0;
+    while i < 3 + alpha as usize {
+        result[i] = if compact {
+            match digit(lit[i]) {
+                Ok(x) => x * 17,
+                Err(e) => return Err(e),
+            }
+        } else {
+            match (digit(lit[i * 2]), digit(lit[i * 2 + 1])) {
+                (Ok(a), Ok(b)) => (a << 4) + b,
+                (Err(e), _) | (_, Err(e)) => return Err(e),
+            }
+        };
+        i += 1;
+    }
+    Ok((result, alpha))
+}
+
+/// Parse hex digits like `AABBCC` or `AABBCCDD`, and `ABC` or `ABCD` with the `compact` feature.
+pub(crate) fn parse_hex(digits: &str, ctx: &Context) -> Result<Color> {
+    match decode(digits.as_bytes()) {
+        Ok(([r, g, b, a], alpha)) => {
+            let a = if alpha { a as i64 } else { ctx.alpha_int() };
+            Ok(Color::Ints([r as i64, g as i64, b as i64, a]))
+        },
+        Err(e) => Err(ParseError::new(e.to_owned())),
+    }
+}
+
+/// Parse a hex color like `"#FF8800"` or `"#FF880080"` into `[r, g, b, a]` in a `const`.
+///
+/// Follows the rules of hex colors in the macros, `#` and `0x` prefixes are optional and
+/// `"#F80"` or `"#F808"` need the `compact` feature. Invalid input panics, a compile error in a `const`.
+///
+/// ```
+/// const ORANGE: [u8; 4] = colorthis_core::hex("#FF8800");
+/// assert_eq!(ORANGE, [255, 136, 0, 255]);
+/// assert_eq!(colorthis_core::hex("0xFF880080"), [255, 136, 0, 128]);
+/// ```
+pub const fn hex(s: &str) -> [u8; 4] {
+    let digits = match s.as_bytes() {
+        [b'#', digits @ ..] | [b'0', b'x' | b'X', digits @ ..] => digits,
+        digits => digits,
+    };
+    match decode(digits) {
+        Ok((color, _)) => color,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::hex;
+
+    #[test]
+    pub fn test_hex() {
+        const COLORS: [[u8; 4]; 3] = [hex("#336699"), hex("33669980"), hex("0Xabcdef01")];
+        assert_eq!(COLORS, [[0x33, 0x66, 0x99, 255], [0x33, 0x66, 0x99, 0x80], [0xAB, 0xCD, 0xEF, 1]]);
+        #[cfg(feature="compact")]
+        assert_eq!(hex("#F808"), [255, 136, 0, 136]);
+        for invalid in ["#12345", "#GG0000", "", "#"] {
+            assert!(std::panic::catch_unwind(|| hex(invalid)).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! The color grammar of [`colorthis`](https://docs.rs/colorthis), as a regular library.
+//!
+//! The `colorthis` macros parse colors with this crate, so a string parsed at runtime
+//! follows the same grammar as the same tokens in a macro. Names and `alpha` from `colorthis.toml`
+//! don't apply here, and [`Rgba::to_u8`] truncates like `round = trunc, scale = 255`.
+//!
+//! ```
+//! use colorthis_core::{parse, Rgba};
+//!
+//! assert_eq!(parse("#AABBCC").unwrap().to_u8(), [0xAA, 0xBB, 0xCC, 0xFF]);
+//! assert_eq!(parse("[0.3; 3, 0.8]").unwrap().to_u8(), [76, 76, 76, 204]);
+//! # #[cfg(feature="tailwind")]
+//! assert_eq!(parse("Sky400 / 50").unwrap().to_u8(), [56, 189, 248, 127]);
+//! assert_eq!(parse("hsl[210deg, 0.4, 0.5]").unwrap(), parse("hsl(210deg 40% 50%)").unwrap());
+//! # #[cfg(feature="parse-color")]
+//! assert_eq!("mix(Red, Blue, 30%)".parse::<Rgba>().unwrap(), parse("mix(Red, Blue, 0.3)").unwrap());
+//! assert!(parse("[300, 0, 0]").is_err());
+//! ```
+//!
+//! Strings are what would be written inside a macro, quotes included, so both `#AABBCC`
+//! and `"AABBCC"` are hex colors. Expressions like `(r, g, b)` are only supported by the macros.
+//!
+//! [`hex`] parses hex colors in a `const`, for crates that avoid proc macros.
+//!
+//! ```
+//! const BRAND: [u8; 4] = colorthis_core::hex("#1A73E8");
+//! ```
+//!
+//! [`FromColorThis`] is the trait behind `colorthis::color!`, implemented by `#[derive(colorthis::FromColorThis)]`.
+//!
+//! # Feature Flags
+//!
+//! * `tailwind` (default): parse names like `Red400` as TailwindCSS colors.
+//! * `compact`: parse hex colors with 3 and 4 digits.
+//! * `serde`: deserialize colors in any syntax, see [`serde`](crate::serde).
+
+use std::fmt;
+
+/// Return a [`ParseError`] from the current function.
+macro_rules! bail {
+    ($($tt:tt)*) => {
+        return Err($crate::ParseError::new(format!($($tt)*)))
+    };
+}
+
+mod channels;
+mod css;
+mod hex;
+mod names;
+mod ops;
+mod syntax;
+#[cfg(feature="serde")]
+pub mod serde;
+#[doc(hidden)]
+pub mod gradient;
+#[doc(hidden)]
+pub mod space;
+
+#[doc(hidden)]
+pub use names::builtin_color;
+pub use channels::{Channels, FromColorThis};
+pub use hex::hex;
+pub use syntax::parse_color;
+
+/// Error of parsing a color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: String) -> Self {
+        ParseError { message }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub type Result<T, E = ParseError> = std::result::Result<T, E>;
+
+/// A color as written, ints in `0..=255` or floats in `0.0..=1.0`.
+///
+/// The macros keep this distinction, `[255, 0, 0]` is output as ints and `[1.0, 0.0, 0.0]` as floats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Ints([i64; 4]),
+    Floats([f64; 4]),
+}
+
+impl Color {
+    /// Channels as floats in `0.0..=1.0`.
+    pub fn to_floats(self) -> [f64; 4] {
+        match self {
+            Color::Ints(ints) => ints.map(|x| x as f64 / 255.0),
+            Color::Floats(floats) => floats,
+        }
+    }
+
+    /// Checks if ints are in `0..=255` and floats in `0.0..=1.0`.
+    pub fn in_bounds(&self) -> bool {
+        match self {
+            Color::Ints(i) => i.iter().all(|x| (0..=255).contains(x)),
+            Color::Floats(f) => f.iter().all(|x| (0.0..=1.0).contains(x)),
+        }
+    }
+
+    /// Fail if out of bounds and `Check::Checked`, or clamp if `Check::Clamp`.
+    pub fn check(self, check: Check) -> Result<Color> {
+        match (check, self) {
+            (Check::Checked, color) if !color.in_bounds() => bail!("Color out of bounds: {:?}", color),
+            (Check::Clamp, Color::Ints(i)) => Ok(Color::Ints(i.map(|x| x.clamp(0, 255)))),
+            (Check::Clamp, Color::Floats(f)) => Ok(Color::Floats(f.map(|x| x.clamp(0.0, 1.0)))),
+            (_, color) => Ok(color),
+        }
+    }
+}
+
+/// Looks up a custom color name, `None` if not found.
+pub type Names<'t> = &'t dyn Fn(&str) -> Option<Result<Color>>;
+
+/// How [`Color::check`] handles channels outside of `0..=255` and `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Check {
+    #[default]
+    Checked,
+    Unchecked,
+    Clamp,
+}
+
+/// Settings of [`parse_color`], the macros fill these in from `colorthis.toml` and feature flags.
+#[derive(Clone, Copy)]
+pub struct Context<'t> {
+    /// Reject numbers outside of `0..=255` and `0.0..=1.0`.
+    pub checked: bool,
+    /// Reject colors outside of the sRGB gamut instead of gamut mapping them.
+    pub strict_gamut: bool,
+    /// Alpha of colors without an alpha channel, in `0.0..=1.0`.
+    pub alpha: f64,
+    /// Custom color names, looked up before builtin names.
+    pub names: Option<Names<'t>>,
+}
+
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Context {
+            checked: true,
+            strict_gamut: false,
+            alpha: 1.0,
+            names: None,
+        }
+    }
+}
+
+impl Context<'_> {
+    /// Alpha of colors without an alpha channel, in `0..=255`.
+    pub(crate) fn alpha_int(&self) -> i64 {
+        (self.alpha * 255.0).round() as i64
+    }
+}
+
+/// A color with `f32` channels in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+/// Tolerance for rounding errors in [`Rgba::to_u8`].
+const QUANTIZE_EPSILON: f64 = 1e-4;
+
+impl Rgba {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Rgba { r, g, b, a }
+    }
+
+    pub const fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// Convert to `0..=255`, truncating like `round = trunc, scale = 255` in the macros.
+    pub fn to_u8(self) -> [u8; 4] {
+        self.to_array().map(|x| {
+            let scaled = x as f64 * 255.0;
+            let near = scaled.round();
+            let x = if (scaled - near).abs() <= QUANTIZE_EPSILON { near } else { scaled.trunc() };
+            x.clamp(0.0, 255.0) as u8
+        })
+    }
+}
+
+impl From<Color> for Rgba {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.to_floats().map(|x| x as f32);
+        Rgba { r, g, b, a }
+    }
+}
+
+impl std::str::FromStr for Rgba {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse(s)
+    }
+}
+
+/// Parse a color with the same syntax as the `colorthis` macros.
+pub fn parse(s: &str) -> Result<Rgba> {
+    Ok(parse_color(s, &Context::default())?.check(Check::Checked)?.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_parse() {
+        assert_eq!(parse("\"#FF8800\"").unwrap().to_u8(), [255, 136, 0, 255]);
+        assert_eq!(parse("0xFF880080").unwrap().to_u8(), [255, 136, 0, 128]);
+        assert_eq!(parse("[255, 136, 0]").unwrap().to_u8(), [255, 136, 0, 255]);
+        assert_eq!(parse("[1.0, -0.0, 0.5f32, 1]").unwrap().to_u8(), [255, 0, 127, 255]);
+        assert_eq!(parse("[128u8; 4]").unwrap().to_u8(), [128; 4]);
+        assert_eq!(parse("rgb(255 136 0 / 50%)").unwrap().to_u8(), [255, 136, 0, 127]);
+        assert_eq!(parse("\"rgb(255 136 0 / 50%)\"").unwrap().to_u8(), [255, 136, 0, 127]);
+        #[cfg(feature="parse-color")]
+        assert_eq!(parse("lighten(Black, 100%)").unwrap().to_u8(), [255; 4]);
+        #[cfg(feature="parse-color")]
+        assert_eq!(parse("  red ").unwrap().to_u8(), [255, 0, 0, 255]);
+        for invalid in ["", "#12345", "[1, 2]", "[256, 0, 0]", "(r, g, b)", "NotAColor", "hsl[1, 2]", "Red /"] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    pub fn test_context() {
+        let names = |name: &str| (name == "Brand").then_some(Ok(Color::Ints([1, 2, 3, 4])));
+        let ctx = Context { alpha: 0.5, names: Some(&names), ..Default::default() };
+        assert_eq!(parse_color("Brand", &ctx), Ok(Color::Ints([1, 2, 3, 4])));
+        assert_eq!(parse_color("#010203", &ctx), Ok(Color::Ints([1, 2, 3, 128])));
+        assert_eq!(parse_color("[0.5; 3]", &ctx), Ok(Color::Floats([0.5; 4])));
+        #[cfg(feature="tailwind")]
+        assert_eq!(parse_color("Red500", &ctx), Ok(Color::Ints([239, 68, 68, 128])));
+        #[cfg(feature="parse-color")]
+        assert_eq!(parse_color("Red", &ctx), Ok(Color::Ints([255, 0, 0, 128])));
+        #[cfg(feature="parse-color")]
+        assert_eq!(parse_color("Transparent", &ctx), Ok(Color::Ints([0, 0, 0, 0])));
+        let ctx = Context { strict_gamut: true, ..Default::default() };
+        assert!(parse_color("oklch[0.7, 0.4, 30]", &ctx).is_err());
+        // Derived colors are gamut mapped even if strict.
+        assert!(parse_color("lighten([0.0, 0.6, 1.0], 10%)", &ctx).is_ok());
+        assert!(parse_color("\"color-mix(in oklch, #FF0000, #0000FF)\"", &ctx).is_ok());
+    }
+}
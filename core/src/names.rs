@@ -0,0 +1,18 @@
+/// Look up a builtin color name, including TailwindCSS names like `Red400`.
+pub fn builtin_color(name: &str) -> Option<[u8; 4]> {
+    #[cfg(feature="tailwind")]
+    if let Some(num) = name.find(|x: char| x.is_ascii_digit()) {
+        let (color, right) = name.split_at(num);
+        if let Ok(index) = right.parse() {
+            if let Some(color) = parse_color::parse_tailwind(color, index){
+                return Some(color);
+            }
+        }
+    }
+    #[cfg(feature="parse-color")]
+    if let Some(color) = parse_color::parse(name) {
+        return Some(color);
+    }
+    let _ = name;
+    None
+}
@@ -0,0 +1,71 @@
+use crate::{Color, Context, Result};
+use crate::gradient::{self, HueMethod, Space};
+use crate::space::{self, Unit};
+use crate::syntax::{component, parse_color, split};
+
+/// Checks if `name` is a color function supported by [`function`].
+pub(crate) fn is_function(name: &str) -> bool {
+    matches!(name, "mix" | "lighten" | "darken" | "saturate" | "desaturate" | "with_alpha")
+}
+
+/// Parse a fraction like `30%` or `0.3`.
+fn fraction(s: &str) -> Result<f64> {
+    component(s)?.alpha()
+}
+
+/// Adjust a color in OKLCH, alpha is kept.
+///
+/// Results are always gamut mapped, `strict-gamut` only rejects colors as written.
+fn oklch(rgba: [f64; 4], f: impl FnOnce([f64; 3]) -> [f64; 3]) -> Color {
+    let [l, c, h] = f(space::srgb_to_oklch([rgba[0], rgba[1], rgba[2]]));
+    let [r, g, b] = space::gamut_map([l.clamp(0.0, 1.0), c.max(0.0), h]);
+    Color::Floats([r, g, b, rgba[3]])
+}
+
+/// Evaluate a color function like `mix(Red, Blue, 30%)` or `lighten(Sky400, 10%)`.
+pub(crate) fn function(name: &str, args: &str, ctx: &Context) -> Result<Color> {
+    let mut args = split(args, ',').into_iter();
+    let mut next = |what: &str| match args.next() {
+        Some(arg) if !arg.trim().is_empty() => Ok(arg),
+        _ => bail!("Expected {} in {}().", what, name),
+    };
+    let color = |s: &str| parse_color(s, ctx).map(Color::to_floats);
+    let result = match name {
+        "mix" => {
+            let a = color(next("a color")?)?;
+            let b = color(next("a second color")?)?;
+            let t = args.next().map(fraction).transpose()?.unwrap_or(0.5);
+            Color::Floats(gradient::interpolate(a, b, t, Space::Oklab, HueMethod::Shorter))
+        },
+        "lighten" | "darken" | "saturate" | "desaturate" => {
+            let rgba = color(next("a color")?)?;
+            let amount = fraction(next("an amount like 10%")?)?;
+            match name {
+                "lighten" => oklch(rgba, |[l, c, h]| [l + amount, c, h]),
+                "darken" => oklch(rgba, |[l, c, h]| [l - amount, c, h]),
+                "saturate" => oklch(rgba, |[l, c, h]| [l, c * (1.0 + amount), h]),
+                _ => oklch(rgba, |[l, c, h]| [l, c * (1.0 - amount), h]),
+            }
+        },
+        "with_alpha" => {
+            let [r, g, b, _] = color(next("a color")?)?;
+            Color::Floats([r, g, b, fraction(next("an alpha like 0.4")?)?])
+        },
+        _ => bail!("Unknown color function {}, expected one of mix, lighten, darken, saturate, desaturate or with_alpha.", name),
+    };
+    if let Some(arg) = args.next() {
+        bail!("Unexpected argument {} in {}().", arg.trim(), name)
+    }
+    Ok(result)
+}
+
+/// Apply a Tailwind style opacity like `Red / 50`, in percent.
+pub(crate) fn opacity(color: Color, s: &str) -> Result<Color> {
+    let [r, g, b, _] = color.to_floats();
+    let opacity = component(s)?;
+    let alpha = match opacity.unit {
+        Unit::Number | Unit::Percent => opacity.value / 100.0,
+        _ => bail!("Expected an opacity in percent like `/ 50`."),
+    };
+    Ok(Color::Floats([r, g, b, alpha]))
+}
@@ -0,0 +1,192 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! Colors are deserialized from any string [`parse`](crate::parse) accepts,
+//! or from arrays like `[255, 136, 0]` and `[1.0, 0.5, 0.0, 0.5]` with the rules of bracketed numbers.
+//!
+//! ```
+//! # use colorthis_core::Rgba;
+//! #[derive(serde::Deserialize)]
+//! struct Theme {
+//!     background: colorthis_core::serde::Color,
+//!     #[serde(deserialize_with = "colorthis_core::serde::deserialize_u8")]
+//!     accent: [u8; 4],
+//!     #[serde(deserialize_with = "colorthis_core::serde::deserialize_clamped")]
+//!     text: Rgba,
+//! }
+//!
+//! let theme: Theme = serde_json::from_str(r#"{
+//!     "background": "Slate900",
+//!     "accent": [255, 136, 0],
+//!     "text": [1.2, 1.0, 1.0]
+//! }"#).unwrap();
+//! assert_eq!(theme.background.to_u8(), [15, 23, 42, 255]);
+//! assert_eq!(theme.accent, [255, 136, 0, 255]);
+//! assert_eq!(theme.text, Rgba::new(1.0, 1.0, 1.0, 1.0));
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use crate::{parse_color, Check, Context, Rgba};
+
+/// A color deserialized from any colorthis syntax, out of bounds colors are errors.
+///
+/// Serialized as a hex string like `"#FF8800FF"`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color(pub Rgba);
+
+impl Deref for Color {
+    type Target = Rgba;
+
+    fn deref(&self) -> &Rgba {
+        &self.0
+    }
+}
+
+impl From<Rgba> for Color {
+    fn from(color: Rgba) -> Self {
+        Color(color)
+    }
+}
+
+impl From<Color> for Rgba {
+    fn from(color: Color) -> Self {
+        color.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Color)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = self.to_u8();
+        serializer.serialize_str(&format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a))
+    }
+}
+
+struct Visitor(Check);
+
+impl Visitor {
+    fn parse<E: de::Error>(&self, s: &str) -> Result<Rgba, E> {
+        let ctx = Context { checked: self.0 == Check::Checked, ..Default::default() };
+        parse_color(s, &ctx)
+            .and_then(|color| color.check(self.0))
+            .map(Rgba::from)
+            .map_err(E::custom)
+    }
+}
+
+impl<'de> de::Visitor<'de> for Visitor {
+    type Value = Rgba;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a color string or an array of 3 or 4 numbers")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Rgba, E> {
+        self.parse(s)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Rgba, A::Error> {
+        let mut numbers = Vec::new();
+        while let Some(number) = seq.next_element::<Number>()? {
+            numbers.push(match number {
+                Number::Int(i) => i.to_string(),
+                // Debug keeps the decimal point of `1.0`.
+                Number::Float(f) => format!("{:?}", f),
+            });
+        }
+        self.parse(&format!("[{}]", numbers.join(", ")))
+    }
+}
+
+/// A number in an array, ints and floats are distinct like in `[255, 0, 0]` and `[1.0, 0.0, 0.0]`.
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl de::Visitor<'_> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Number, E> {
+                Ok(Number::Int(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Number, E> {
+                i64::try_from(v).map(Number::Int).map_err(|_| E::custom("Integer overflow."))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Number, E> {
+                Ok(Number::Float(v))
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+/// Deserialize a color, out of bounds colors are errors.
+///
+/// Use with `#[serde(deserialize_with = "colorthis_core::serde::deserialize")]`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgba, D::Error> {
+    deserializer.deserialize_any(Visitor(Check::Checked))
+}
+
+/// Deserialize a color, out of bounds channels are clamped.
+pub fn deserialize_clamped<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgba, D::Error> {
+    deserializer.deserialize_any(Visitor(Check::Clamp))
+}
+
+/// Deserialize a color as `[r, g, b, a]` in `0..=255`.
+pub fn deserialize_u8<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 4], D::Error> {
+    deserialize(deserializer).map(Rgba::to_u8)
+}
+
+/// Deserialize a color as `[r, g, b, a]` in `0.0..=1.0`.
+pub fn deserialize_f32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f32; 4], D::Error> {
+    deserialize(deserializer).map(Rgba::to_array)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Color;
+
+    #[test]
+    pub fn test_serde() {
+        let color = |s: &str| serde_json::from_str::<Color>(s).map(|x| x.to_u8());
+        assert_eq!(color(r##""#FF8800""##).unwrap(), [255, 136, 0, 255]);
+        assert_eq!(color(r#""rgb(255 136 0 / 50%)""#).unwrap(), [255, 136, 0, 127]);
+        assert_eq!(color(r#""[0.5; 3]""#).unwrap(), [127, 127, 127, 255]);
+        assert_eq!(color("[255, 136, 0, 128]").unwrap(), [255, 136, 0, 128]);
+        assert_eq!(color("[1.0, 0.5, 0, 1]").unwrap(), [255, 127, 0, 255]);
+        assert_eq!(color("[1e0, 0, 0]").unwrap(), [255, 0, 0, 255]);
+        assert!(color("[256, 0, 0]").is_err());
+        assert!(color("[1.5, 0.0, 0.0]").is_err());
+        assert!(color("[1, 2]").is_err());
+        assert!(color("12").is_err());
+        #[derive(serde::Deserialize)]
+        struct Theme {
+            #[serde(deserialize_with = "super::deserialize_f32")]
+            a: [f32; 4],
+            b: Color,
+        }
+        let theme: Theme = toml::from_str("a = [0.0, 0.5, 1.0, 0.5]\nb = \"#FF8800\"").unwrap();
+        assert_eq!(theme.a, [0.0, 0.5, 1.0, 0.5]);
+        assert_eq!(theme.b.to_u8(), [255, 136, 0, 255]);
+        let json = serde_json::to_string(&Color(crate::parse("#FF8800").unwrap())).unwrap();
+        assert_eq!(json, r##""#FF8800FF""##);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{Color, Context, Result};
+
+/// Unit of a channel value in a color space syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unit {
+    Number,
+    Percent,
+    Dimension(String),
+    /// The CSS keyword `none`, a missing component.
+    None,
+}
+
+/// A channel value in a color space syntax like `hsl(210deg 40% 50%)` or `hsl[210, 0.4, 0.5]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+/// The syntax a color space was written in,
+/// bracketed syntax uses `0.0..=1.0` where CSS uses `0..=100`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    Css,
+    Bracket,
+}
+
+impl Component {
+    /// Convert a hue to degrees.
+    fn hue(&self) -> Result<f64> {
+        Ok(match &self.unit {
+            Unit::Number => self.value,
+            Unit::None => 0.0,
+            Unit::Dimension(unit) => match unit.as_str() {
+                "deg" => self.value,
+                "rad" => self.value.to_degrees(),
+                "grad" => self.value * 0.9,
+                "turn" => self.value * 360.0,
+                _ => bail!("Unknown angle unit {}, expected one of deg, rad, grad or turn.", unit),
+            },
+            Unit::Percent => bail!("Expected a hue, found percentage."),
+        })
+    }
+
+    /// Convert a percentage-like value to `0.0..=1.0`.
+    fn fraction(&self, syntax: Syntax) -> Result<f64> {
+        Ok(match (&self.unit, syntax) {
+            (Unit::Number, Syntax::Css) => self.value / 100.0,
+            (Unit::Number, Syntax::Bracket) => self.value,
+            (Unit::Percent, _) => self.value / 100.0,
+            (Unit::None, _) => 0.0,
+            (Unit::Dimension(unit), _) => bail!("Expected number or percentage, found unit {}.", unit),
+        })
+    }
+
+    /// Convert a number or a percentage of `reference`.
+    fn scaled(&self, reference: f64) -> Result<f64> {
+        Ok(match &self.unit {
+            Unit::Number => self.value,
+            Unit::Percent => self.value / 100.0 * reference,
+            Unit::None => 0.0,
+            Unit::Dimension(unit) => bail!("Expected number or percentage, found unit {}.", unit),
+        })
+    }
+
+    /// Convert an alpha value to `0.0..=1.0`.
+    pub fn alpha(&self) -> Result<f64> {
+        self.fraction(Syntax::Bracket)
+    }
+}
+
+/// Converts HSL to sRGB, hue in degrees, saturation and lightness in `0.0..=1.0`.
+pub fn hsl_to_srgb(hue: f64, sat: f64, light: f64) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.0);
+    let f = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = sat * light.min(1.0 - light);
+        light - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+/// Converts HWB to sRGB, hue in degrees, whiteness and blackness in `0.0..=1.0`.
+pub fn hwb_to_srgb(hue: f64, white: f64, black: f64) -> [f64; 3] {
+    if white + black >= 1.0 {
+        let gray = white / (white + black);
+        return [gray, gray, gray];
+    }
+    hsl_to_srgb(hue, 1.0, 0.5).map(|x| x * (1.0 - white - black) + white)
+}
+
+/// Applies the sRGB transfer function to a linear value.
+pub fn srgb_encode(x: f64) -> f64 {
+    if x.abs() <= 0.0031308 {
+        x * 12.92
+    } else {
+        x.signum() * (1.055 * x.abs().powf(1.0 / 2.4) - 0.055)
+    }
+}
+
+/// Removes the sRGB transfer function, producing a linear value.
+pub fn srgb_decode(x: f64) -> f64 {
+    if x.abs() <= 0.04045 {
+        x / 12.92
+    } else {
+        x.signum() * ((x.abs() + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts OKLab to linear sRGB.
+pub fn oklab_to_linear(lab: [f64; 3]) -> [f64; 3] {
+    let [l, a, b] = lab;
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+    let (l, m, s) = (l_.powi(3), m_.powi(3), s_.powi(3));
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+/// Converts linear sRGB to OKLab.
+pub fn linear_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb;
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// Converts polar coordinates like `LCH` to rectangular coordinates like `Lab`, hue in degrees.
+pub fn lch_to_lab(lch: [f64; 3]) -> [f64; 3] {
+    let [l, c, h] = lch;
+    let (sin, cos) = h.to_radians().sin_cos();
+    [l, c * cos, c * sin]
+}
+
+fn mul(m: [[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// The D50 white point in XYZ.
+const D50: [f64; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+/// Bradford chromatic adaptation from D50 to D65.
+const D50_TO_D65: [[f64; 3]; 3] = [
+    [0.955473421488075, -0.02309845494876471, 0.06325924320057072],
+    [-0.0283697093338637, 1.0099953980813041, 0.021041441191917323],
+    [0.012314014864481998, -0.020507649298898964, 1.330365926242124],
+];
+
+/// XYZ with the D65 white point to linear sRGB.
+const XYZ_TO_LINEAR: [[f64; 3]; 3] = [
+    [12831.0 / 3959.0, -329.0 / 214.0, -1974.0 / 3959.0],
+    [-851781.0 / 878810.0, 1648619.0 / 878810.0, 36519.0 / 878810.0],
+    [705.0 / 12673.0, -2585.0 / 12673.0, 705.0 / 667.0],
+];
+
+/// Converts CIE Lab with the D50 white point to XYZ with the D50 white point.
+pub fn lab_to_xyz(lab: [f64; 3]) -> [f64; 3] {
+    const KAPPA: f64 = 24389.0 / 27.0;
+    const E: f64 = 216.0 / 24389.0;
+    let [l, a, b] = lab;
+    let f1 = (l + 16.0) / 116.0;
+    let f0 = a / 500.0 + f1;
+    let f2 = f1 - b / 200.0;
+    let x = if f0.powi(3) > E { f0.powi(3) } else { (116.0 * f0 - 16.0) / KAPPA };
+    let y = if l > KAPPA * E { f1.powi(3) } else { l / KAPPA };
+    let z = if f2.powi(3) > E { f2.powi(3) } else { (116.0 * f2 - 16.0) / KAPPA };
+    [x * D50[0], y * D50[1], z * D50[2]]
+}
+
+/// Linear sRGB to XYZ with the D65 white point.
+const LINEAR_TO_XYZ: [[f64; 3]; 3] = [
+    [506752.0 / 1228815.0, 87881.0 / 245763.0, 12673.0 / 70218.0],
+    [87098.0 / 409605.0, 175762.0 / 245763.0, 12673.0 / 175545.0],
+    [7918.0 / 409605.0, 87881.0 / 737289.0, 1001167.0 / 1053270.0],
+];
+
+/// Bradford chromatic adaptation from D65 to D50.
+const D65_TO_D50: [[f64; 3]; 3] = [
+    [1.0479297925449969, 0.022946870601609652, -0.05019226628920524],
+    [0.02962780877005599, 0.9904344267538799, -0.017073799063418826],
+    [-0.009243040646204504, 0.015055191490298152, 0.7518742814281371],
+];
+
+/// Converts XYZ with the D50 white point to CIE Lab with the D50 white point.
+pub fn xyz_to_lab(xyz: [f64; 3]) -> [f64; 3] {
+    const KAPPA: f64 = 24389.0 / 27.0;
+    const E: f64 = 216.0 / 24389.0;
+    let [x, y, z] = [xyz[0] / D50[0], xyz[1] / D50[1], xyz[2] / D50[2]]
+        .map(|v| if v > E { v.cbrt() } else { (KAPPA * v + 16.0) / 116.0 });
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+/// Converts rectangular coordinates like `Lab` to polar coordinates like `LCH`, hue in degrees.
+pub fn lab_to_lch(lab: [f64; 3]) -> [f64; 3] {
+    let [l, a, b] = lab;
+    let chroma = a.hypot(b);
+    if chroma <= EPSILON {
+        return [l, 0.0, 0.0];
+    }
+    [l, chroma, b.atan2(a).to_degrees().rem_euclid(360.0)]
+}
+
+/// Converts sRGB to CIE Lab with the D50 white point.
+pub fn srgb_to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    xyz_to_lab(mul(D65_TO_D50, mul(LINEAR_TO_XYZ, rgb.map(srgb_decode))))
+}
+
+/// Converts sRGB to OKLCH.
+pub fn srgb_to_oklch(rgb: [f64; 3]) -> [f64; 3] {
+    lab_to_lch(srgb_to_oklab(rgb))
+}
+
+/// Hue in degrees, max and min of a sRGB color.
+fn hue_max_min(rgb: [f64; 3]) -> (f64, f64, f64) {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let d = max - min;
+    let hue = if d <= EPSILON {
+        0.0
+    } else if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (hue * 60.0, max, min)
+}
+
+/// Converts sRGB to HSL, hue in degrees, saturation and lightness in `0.0..=1.0`.
+pub fn srgb_to_hsl(rgb: [f64; 3]) -> [f64; 3] {
+    let (hue, max, min) = hue_max_min(rgb);
+    let light = (max + min) / 2.0;
+    let sat = if max - min <= EPSILON || light <= 0.0 || light >= 1.0 {
+        0.0
+    } else {
+        (max - light) / light.min(1.0 - light)
+    };
+    [hue, sat, light]
+}
+
+/// Converts sRGB to HSV, hue in degrees, saturation and value in `0.0..=1.0`.
+pub fn srgb_to_hsv(rgb: [f64; 3]) -> [f64; 3] {
+    let (hue, max, min) = hue_max_min(rgb);
+    let sat = if max <= 0.0 { 0.0 } else { (max - min) / max };
+    [hue, sat, max]
+}
+
+/// Converts CIE Lab with the D50 white point to sRGB.
+fn lab_to_srgb(lab: [f64; 3]) -> [f64; 3] {
+    snap(mul(XYZ_TO_LINEAR, mul(D50_TO_D65, lab_to_xyz(lab))).map(srgb_encode))
+}
+
+fn oklab_to_srgb(lab: [f64; 3]) -> [f64; 3] {
+    oklab_to_linear(lab).map(srgb_encode)
+}
+
+pub fn srgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    linear_to_oklab(rgb.map(srgb_decode))
+}
+
+/// Tolerance for rounding errors in color space conversions.
+const EPSILON: f64 = 1e-6;
+
+fn in_gamut(rgb: [f64; 3]) -> bool {
+    rgb.iter().all(|x| (-EPSILON..=1.0 + EPSILON).contains(x))
+}
+
+/// Snap values off by rounding errors to `0.0` and `1.0`.
+pub fn snap(rgb: [f64; 3]) -> [f64; 3] {
+    rgb.map(|x| match x {
+        x if x.abs() <= EPSILON => 0.0,
+        x if (x - 1.0).abs() <= EPSILON => 1.0,
+        x => x,
+    })
+}
+
+fn delta_eok(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>().sqrt()
+}
+
+/// Maps an OKLCH color into the sRGB gamut, using the binary search algorithm in CSS Color 4.
+pub fn gamut_map(lch: [f64; 3]) -> [f64; 3] {
+    const JND: f64 = 0.02;
+    const MIN_DELTA: f64 = 0.0001;
+    let [l, c, h] = lch;
+    if l >= 1.0 {
+        return [1.0; 3];
+    }
+    if l <= 0.0 {
+        return [0.0; 3];
+    }
+    let origin = oklab_to_srgb(lch_to_lab(lch));
+    if in_gamut(origin) {
+        return snap(origin);
+    }
+    let clip = |rgb: [f64; 3]| rgb.map(|x| x.clamp(0.0, 1.0));
+    let mut clipped = clip(origin);
+    if delta_eok(srgb_to_oklab(clipped), lch_to_lab(lch)) < JND {
+        return clipped;
+    }
+    let (mut min, mut max) = (0.0, c);
+    let mut min_in_gamut = true;
+    while max - min > MIN_DELTA {
+        let chroma = (min + max) / 2.0;
+        let current = lch_to_lab([l, chroma, h]);
+        let rgb = oklab_to_srgb(current);
+        if min_in_gamut && in_gamut(rgb) {
+            min = chroma;
+            continue;
+        }
+        clipped = clip(rgb);
+        let delta = delta_eok(srgb_to_oklab(clipped), current);
+        if delta < JND {
+            if JND - delta < MIN_DELTA {
+                return clipped;
+            }
+            min_in_gamut = false;
+            min = chroma;
+        } else {
+            max = chroma;
+        }
+    }
+    clipped
+}
+
+/// Converts an OKLCH color to sRGB, gamut mapping or failing if out of gamut and `strict`.
+pub fn oklch_to_srgb(lch: [f64; 3], strict: bool) -> Result<[f64; 3]> {
+    let rgb = oklab_to_srgb(lch_to_lab(lch));
+    if in_gamut(rgb) {
+        return Ok(snap(rgb));
+    }
+    if strict {
+        bail!("Color is outside of the sRGB gamut.")
+    }
+    Ok(gamut_map(lch))
+}
+
+/// Checks if `name` is a color space supported by [`parse_space`].
+pub fn is_space(name: &str) -> bool {
+    matches!(name, "hsl" | "hsla" | "hwb" | "oklab" | "oklch" | "lab" | "lch")
+}
+
+/// Parse channels of a color space into a srgb color.
+pub fn parse_space(name: &str, channels: &[Component], alpha: Option<&Component>, syntax: Syntax, ctx: &Context) -> Result<Color> {
+    let [a, b, c] = channels else {
+        bail!("Expected 3 channels in {}, found {}.", name, channels.len())
+    };
+    let strict = ctx.strict_gamut;
+    let [r, g, b] = match name {
+        "hsl" | "hsla" => hsl_to_srgb(a.hue()?, b.fraction(syntax)?, c.fraction(syntax)?),
+        "hwb" => hwb_to_srgb(a.hue()?, b.fraction(syntax)?, c.fraction(syntax)?),
+        "oklab" => {
+            let [l, a, b] = [a.scaled(1.0)?, b.scaled(0.4)?, c.scaled(0.4)?];
+            oklch_to_srgb([l, a.hypot(b), b.atan2(a).to_degrees()], strict)?
+        },
+        "oklch" => oklch_to_srgb([a.scaled(1.0)?, b.scaled(0.4)?, c.hue()?], strict)?,
+        "lab" => lab_to_srgb([a.scaled(100.0)?, b.scaled(125.0)?, c.scaled(125.0)?]),
+        "lch" => lab_to_srgb(lch_to_lab([a.scaled(100.0)?, b.scaled(150.0)?, c.hue()?])),
+        _ => bail!("Unknown color space {}.", name),
+    };
+    let alpha = match alpha {
+        Some(alpha) => alpha.alpha()?,
+        None => ctx.alpha,
+    };
+    Ok(Color::Floats([r, g, b, alpha]))
+}
@@ -0,0 +1,345 @@
+use crate::{Color, Context, Result, css, hex, names, ops};
+use crate::space::{self, Component, Unit, Syntax};
+
+/// Byte offsets of `sep` outside of brackets, parentheses and strings.
+fn top_level(s: &str, sep: char) -> Vec<usize> {
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    let mut string = false;
+    let mut escape = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escape => escape = false,
+            '\\' if string => escape = true,
+            '"' => string = !string,
+            _ if string => (),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => result.push(i),
+            _ => (),
+        }
+    }
+    result
+}
+
+/// Separate a string by top level `sep`, a trailing separator is ignored.
+pub(crate) fn split(s: &str, sep: char) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for i in top_level(s, sep) {
+        result.push(&s[start..i]);
+        start = i + sep.len_utf8();
+    }
+    if !s[start..].trim().is_empty() {
+        result.push(&s[start..]);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KnownToken {
+    Int(i64),
+    Float(f64),
+    Comma,
+    SemiColon,
+}
+
+impl KnownToken {
+    fn cast(&self) -> f64 {
+        match self {
+            Self::Int(i) => *i as f64,
+            Self::Float(f) => *f,
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_f64(&self) -> f64 {
+        match self {
+            Self::Int(i) => *i as f64 / 255.0,
+            Self::Float(f) => *f,
+            _ => unreachable!(),
+        }
+    }
+}
+
+const SUFFIXES: &[&str] = &[
+    "", "u8", "u16", "u32", "u64", "u128", "usize",
+    "i8", "i16", "i32", "i64", "i128", "isize", "f32", "f64",
+];
+
+/// Split a number literal like `-0.5f32`, `0xFF` or `210deg` into its value and suffix.
+fn literal(s: &str) -> Result<(KnownToken, &str)> {
+    let (neg, body) = match s.strip_prefix('-') {
+        Some(body) => (true, body.trim_start()),
+        None => (false, s),
+    };
+    let radix = match body.get(0..2) {
+        Some("0x" | "0X") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    let (token, suffix) = if radix != 10 {
+        let digits = &body[2..];
+        let end = digits.find(|c: char| !c.is_digit(radix) && c != '_').unwrap_or(digits.len());
+        match i64::from_str_radix(&digits[..end].replace('_', ""), radix) {
+            Ok(i) => (KnownToken::Int(i), &digits[end..]),
+            Err(_) => bail!("Expected number literal, found {}.", s),
+        }
+    } else {
+        let bytes = body.as_bytes();
+        let digits = |mut i: usize| {
+            while bytes.get(i).is_some_and(|c| c.is_ascii_digit() || *c == b'_') {
+                i += 1;
+            }
+            i
+        };
+        let mut end = digits(0);
+        let mut float = false;
+        if end > 0 && bytes.get(end) == Some(&b'.') && !bytes.get(end + 1).is_some_and(|c| c.is_ascii_alphabetic()) {
+            float = true;
+            end = digits(end + 1);
+        }
+        if end > 0 && matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let exponent = match &bytes[end + 1..] {
+                [b'0'..=b'9', ..] => end + 1,
+                [b'+' | b'-', b'0'..=b'9', ..] => end + 2,
+                _ => end,
+            };
+            if exponent != end {
+                float = true;
+                end = digits(exponent);
+            }
+        }
+        if end == 0 {
+            bail!("Expected number literal, found {}.", s)
+        }
+        let (number, suffix) = body.split_at(end);
+        let number = number.replace('_', "");
+        if float || suffix.starts_with('f') {
+            match number.parse() {
+                Ok(f) => (KnownToken::Float(f), suffix),
+                Err(_) => bail!("float overflow."),
+            }
+        } else {
+            match number.parse() {
+                Ok(i) => (KnownToken::Int(i), suffix),
+                Err(_) => bail!("Integer overflow."),
+            }
+        }
+    };
+    Ok(match (token, neg) {
+        (KnownToken::Int(i), true) => (KnownToken::Int(-i), suffix),
+        (KnownToken::Float(f), true) => (KnownToken::Float(-f), suffix),
+        (token, _) => (token, suffix),
+    })
+}
+
+/// Parse a number in brackets, checking its range if `ctx.checked`.
+fn number(s: &str, ctx: &Context) -> Result<KnownToken> {
+    let s = s.trim();
+    let (token, suffix) = literal(s)?;
+    if !SUFFIXES.contains(&suffix) {
+        bail!("Expected number literal, found {}.", s)
+    }
+    match token {
+        KnownToken::Int(i) if ctx.checked && !(0..=255).contains(&i) => {
+            bail!("Integer has to be in range 0..=255.")
+        },
+        KnownToken::Float(f) if ctx.checked && !(0.0..=1.0).contains(&f) => {
+            bail!("Float has to be in range 0.0..=1.0.")
+        },
+        token => Ok(token),
+    }
+}
+
+macro_rules! iof {
+    (_) => {
+        KnownToken::Int(_)|KnownToken::Float(_)
+    };
+    ($i: ident) => {
+        $i @ (KnownToken::Int(_)|KnownToken::Float(_))
+    };
+}
+
+/// Parse numbers like `255, 0, 0` or `0.5; 3` inside brackets.
+fn parse_numbers(s: &str, ctx: &Context) -> Result<Color> {
+    use KnownToken::*;
+    let mut tokens = Vec::new();
+    for (i, item) in split(s, ',').into_iter().enumerate() {
+        if i > 0 {
+            tokens.push(Comma);
+        }
+        for (j, item) in item.split(';').enumerate() {
+            if j > 0 {
+                tokens.push(SemiColon);
+            }
+            tokens.push(number(item, ctx)?);
+        }
+    }
+    Ok(match tokens.as_slice() {
+        [Int(a), Comma, Int(b), Comma, Int(c)] => {
+            Color::Ints([*a, *b, *c, ctx.alpha_int()])
+        },
+        [Int(a), Comma, Int(b), Comma, Int(c), Comma, Int(d)] => {
+            Color::Ints([*a, *b, *c, *d])
+        },
+        [iof!(a), Comma, iof!(b), Comma, iof!(c)] => {
+            Color::Floats([a.cast(), b.cast(), c.cast(), ctx.alpha])
+        },
+        [iof!(a), Comma, iof!(b), Comma, iof!(c), Comma, iof!(d)] => {
+            Color::Floats([a.cast(), b.cast(), c.cast(), d.cast()])
+        },
+        [Int(a), SemiColon, Int(b)] => {
+            match b {
+                3 => Color::Ints([*a, *a, *a, ctx.alpha_int()]),
+                4 => Color::Ints([*a, *a, *a, *a]),
+                _ => bail!("Splat can only have value 3 or 4.")
+            }
+        },
+        [Float(a), SemiColon, Int(b)] => {
+            match b {
+                3 => Color::Floats([*a, *a, *a, ctx.alpha]),
+                4 => Color::Floats([*a, *a, *a, *a]),
+                _ => bail!("Splat can only have value 3 or 4.")
+            }
+        },
+        [Int(a), SemiColon, Int(3), Comma, Int(c)] => {
+            Color::Ints([*a, *a, *a, *c])
+        },
+        [iof!(a), SemiColon, Int(3), Comma, iof!(c)] => {
+            Color::Floats([a.parse_f64(), a.parse_f64(), a.parse_f64(), c.parse_f64()])
+        },
+        [iof!(_), SemiColon, _, Comma, iof!(_)] => {
+            bail!("Splat with alpha can only have value 3.")
+        },
+        _ => bail!("Unknown color syntax.")
+    })
+}
+
+/// Parse a channel of a bracketed color space like `210deg`, `0.4` or `40%`.
+pub(crate) fn component(s: &str) -> Result<Component> {
+    let s: String = s.split_whitespace().collect();
+    if s == "none" {
+        return Ok(Component { value: 0.0, unit: Unit::None })
+    }
+    if s.is_empty() {
+        bail!("Expected number literal.")
+    }
+    let (number, percent) = match s.strip_suffix('%') {
+        Some(number) => (number, true),
+        None => (s.as_str(), false),
+    };
+    let (token, suffix) = literal(number)?;
+    let value = match token {
+        KnownToken::Int(i) => i as f64,
+        KnownToken::Float(f) => f,
+        _ => unreachable!(),
+    };
+    let unit = match suffix {
+        _ if percent => Unit::Percent,
+        "deg" | "rad" | "grad" | "turn" => Unit::Dimension(suffix.to_owned()),
+        _ => Unit::Number,
+    };
+    Ok(Component { value, unit })
+}
+
+/// Parse bracketed color spaces like `hsl[210, 0.4, 0.5]`.
+fn parse_space(name: &str, s: &str, ctx: &Context) -> Result<Color> {
+    let space = name.to_ascii_lowercase();
+    if !space::is_space(&space) {
+        bail!("Unknown color space {}.", name)
+    }
+    let mut channels = split(s, ',').into_iter().map(component).collect::<Result<Vec<_>>>()?;
+    let alpha = match channels.len() {
+        3 => None,
+        4 => channels.pop(),
+        _ => bail!("Expected 3 or 4 items."),
+    };
+    space::parse_space(&space, &channels, alpha.as_ref(), Syntax::Bracket, ctx)
+}
+
+/// Parse the contents of a string, hex digits with an optional `#` or a CSS color.
+fn parse_string(s: &str, ctx: &Context) -> Result<Color> {
+    match s.strip_prefix('#') {
+        Some(digits) => hex::parse_hex(digits, ctx),
+        None if s.contains('(') => css::parse_css(s, ctx),
+        None => hex::parse_hex(s, ctx),
+    }
+}
+
+/// Look up a color name, names in `ctx` take priority over builtin names.
+///
+/// Opaque builtin names have no alpha channel and use the alpha of `ctx`, `Transparent` keeps its alpha.
+fn parse_name(name: &str, ctx: &Context) -> Result<Color> {
+    if let Some(color) = ctx.names.and_then(|names| names(name)) {
+        return color
+    }
+    match names::builtin_color(name) {
+        Some([r, g, b, 255]) => Ok(Color::Ints([r.into(), g.into(), b.into(), ctx.alpha_int()])),
+        Some(color) => Ok(Color::Ints(color.map(i64::from))),
+        None => bail!("Invalid color name: {}.", name),
+    }
+}
+
+/// Parse a color as written in a macro, like `"#AABBCC"`, `[0.3; 3]` or `Sky400 / 50`.
+pub fn parse_color(s: &str, ctx: &Context) -> Result<Color> {
+    let s = s.trim();
+    if let Some(&index) = top_level(s, '/').last() {
+        let color = parse_color(&s[..index], ctx)?;
+        return ops::opacity(color, &s[index + 1..])
+    }
+    if let Some(digits) = s.strip_prefix('#') {
+        return hex::parse_hex(digits, ctx)
+    }
+    if let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return hex::parse_hex(digits, ctx)
+    }
+    if let Some(string) = s.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+        return parse_string(string, ctx)
+    }
+    if let Some(numbers) = s.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+        return parse_numbers(numbers, ctx)
+    }
+    if s.starts_with('(') {
+        bail!("Expressions are only supported by the macros, found {}.", s)
+    }
+    let end = s.find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-').unwrap_or(s.len());
+    let (name, rest) = s.split_at(end);
+    let rest = rest.trim_start();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        bail!("Invalid color syntax: {}.", s)
+    }
+    if rest.is_empty() {
+        return parse_name(name, ctx)
+    }
+    if let Some(channels) = rest.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+        return parse_space(name, channels, ctx)
+    }
+    if let Some(args) = rest.strip_prefix('(').and_then(|x| x.strip_suffix(')')) {
+        return match name {
+            name if ops::is_function(name) => ops::function(name, args, ctx),
+            name if css::is_function(&name.to_ascii_lowercase()) => css::parse_css(s, ctx),
+            _ => bail!("Unknown color function {}, expected one of mix, lighten, darken, saturate, desaturate or with_alpha.", name),
+        }
+    }
+    bail!("Invalid color syntax: {}.", s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_literal() {
+        assert_eq!(literal("255u8").unwrap(), (KnownToken::Int(255), "u8"));
+        assert_eq!(literal("- 0.5").unwrap(), (KnownToken::Float(-0.5), ""));
+        assert_eq!(literal("1f32").unwrap(), (KnownToken::Float(1.0), "f32"));
+        assert_eq!(literal("0xFF").unwrap(), (KnownToken::Int(255), ""));
+        assert_eq!(literal("1e-1").unwrap(), (KnownToken::Float(0.1), ""));
+        assert_eq!(literal("0.5turn").unwrap(), (KnownToken::Float(0.5), "turn"));
+        assert_eq!(literal("210deg").unwrap(), (KnownToken::Int(210), "deg"));
+        assert!(literal("deg").is_err());
+        assert_eq!(split("mix(a, b), \"1,2\", [3, 4],", ','), ["mix(a, b)", " \"1,2\"", " [3, 4]"]);
+    }
+}
@@ -0,0 +1,114 @@
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree, Spacing};
+use proc_macro_error::abort;
+use quote::quote;
+use crate::config::config;
+use crate::parse::{parse_color, builtin_color};
+
+/// A color name defined by `define_colors!`.
+#[derive(Debug, Clone)]
+pub(crate) struct CustomColor {
+    pub name: Ident,
+    pub value: TokenStream,
+}
+
+/// Parse `[override] Name = color, ...`, names cannot shadow built-in or configured colors
+/// unless marked `override`.
+pub(crate) fn parse_colors(tokens: TokenStream) -> Vec<CustomColor> {
+    let mut colors: Vec<CustomColor> = Vec::new();
+    let mut iter = tokens.into_iter().peekable();
+    while iter.peek().is_some() {
+        let (overrides, name) = match (iter.next(), iter.peek()) {
+            (Some(TokenTree::Ident(o)), Some(TokenTree::Ident(_))) if o == "override" => {
+                match iter.next() {
+                    Some(TokenTree::Ident(name)) => (true, name),
+                    _ => unreachable!(),
+                }
+            },
+            (Some(TokenTree::Ident(name)), _) => (false, name),
+            (Some(tt), _) => abort!(tt.span(), "Expected color name."),
+            (None, _) => unreachable!(),
+        };
+        match iter.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '=' && p.spacing() == Spacing::Alone => (),
+            _ => abort!(name.span(), "Expected = after color name {}.", name),
+        }
+        let mut value = Vec::new();
+        for tt in iter.by_ref() {
+            match tt {
+                TokenTree::Punct(p) if p.as_char() == ',' => break,
+                tt => value.push(tt),
+            }
+        }
+        if value.is_empty() {
+            abort!(name.span(), "Expected color after {} =.", name)
+        }
+        let value: TokenStream = value.into_iter().collect();
+        let s = name.to_string();
+        if colors.iter().any(|x| x.name == s) {
+            abort!(name.span(), "Duplicate color name {}.", name)
+        }
+        let shadows = if config().color(&s).is_some() {
+            Some("a color in colorthis.toml")
+        } else if builtin_color(&s).is_some() {
+            Some("a built-in color")
+        } else {
+            None
+        };
+        match (shadows, overrides) {
+            (Some(shadows), false) => abort!(name.span(), "{} shadows {}.", name, shadows;
+                help = "use `override {} = ...` to replace it.", name),
+            (None, true) => abort!(name.span(), "{} does not shadow any color, remove `override`.", name),
+            _ => (),
+        }
+        // Report invalid colors at the definition.
+        parse_color(value.clone(), &[]);
+        colors.push(CustomColor { name, value });
+    }
+    colors
+}
+
+/// Expand `define_colors! { macro name; Name = color, ... }` into a `macro_rules!` wrapper
+/// that passes the colors to other macros.
+pub(crate) fn define_colors(tokens: TokenStream) -> TokenStream {
+    let mut iter = tokens.into_iter();
+    let name = match (iter.next(), iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(m)), Some(TokenTree::Ident(name)), Some(TokenTree::Punct(p)))
+            if m == "macro" && p.as_char() == ';' => name,
+        _ => abort!(Span::call_site(), "Expected `macro name;` before colors."),
+    };
+    let colors: TokenStream = iter.collect();
+    parse_colors(colors.clone());
+    quote!(
+        macro_rules! #name {
+            ($mac: ident ! ($($args: tt)*)) => {
+                ::colorthis::__with_colors!({::colorthis::$mac} {#colors} $($args)*)
+            };
+            (:: $($mac: ident)::+ ! ($($args: tt)*)) => {
+                ::colorthis::__with_colors!({:: $($mac)::+} {#colors} $($args)*)
+            };
+            ($($mac: ident)::+ ! ($($args: tt)*)) => {
+                ::colorthis::__with_colors!({$($mac)::+} {#colors} $($args)*)
+            };
+        }
+    )
+}
+
+/// Expand `{macro} {colors} args` into `macro!(args)` with `colors = {colors}` added to the options,
+/// which follow the last `;` for `palette!` and `gradient!`, or the color for the other macros.
+pub(crate) fn with_colors(tokens: TokenStream) -> TokenStream {
+    let mut iter = tokens.into_iter();
+    let (mac, colors) = match (iter.next(), iter.next()) {
+        (Some(TokenTree::Group(mac)), Some(TokenTree::Group(colors)))
+            if mac.delimiter() == Delimiter::Brace && colors.delimiter() == Delimiter::Brace => (mac.stream(), colors.stream()),
+        _ => abort!(Span::call_site(), "Expected `{macro} {colors}` before the arguments."),
+    };
+    let args: Vec<TokenTree> = iter.collect();
+    let sections = args.iter().filter(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ';')).count();
+    // Empty options like `,,` and `;,` are ignored by the macros.
+    let option = quote!(colors = {#colors});
+    let args = match sections {
+        1 => quote!(#(#args)*; #option),
+        _ => quote!(#(#args)*, #option),
+    };
+    quote!(#mac!(#args))
+}
@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::quote;
+use toml::{Table, Value};
+use crate::convert::{Rounding, Scale};
+use crate::parse::builtin_color;
+
+pub(crate) use colorthis_core::Check;
+
+/// Per crate configuration from `colorthis.toml` or `[package.metadata.colorthis]`.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    pub check: Option<Check>,
+    pub round: Option<Rounding>,
+    pub scale: Option<Scale>,
+    pub strict_gamut: Option<bool>,
+    /// Alpha of colors without an alpha channel, in `0.0..=1.0`.
+    pub alpha: Option<f64>,
+    /// Custom color names and their color strings.
+    pub colors: Vec<(String, String)>,
+    /// File the configuration is read from.
+    pub path: Option<PathBuf>,
+}
+
+thread_local! {
+    static CONFIG: RefCell<Option<(PathBuf, Rc<Config>)>> = const { RefCell::new(None) };
+}
+
+/// Configuration of the crate invoking the macro, cached per manifest directory.
+pub(crate) fn config() -> Rc<Config> {
+    let dir = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => return Rc::default(),
+    };
+    CONFIG.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        match cache.as_ref() {
+            Some((cached, config)) if cached == &dir => config.clone(),
+            _ => {
+                let config = Rc::new(load(&dir));
+                *cache = Some((dir, config.clone()));
+                config
+            }
+        }
+    })
+}
+
+/// Read `colorthis.toml`, or `[package.metadata.colorthis]` in `Cargo.toml`.
+fn load(dir: &Path) -> Config {
+    let path = dir.join("colorthis.toml");
+    if let Some(table) = read(&path) {
+        return from_table(table, path)
+    }
+    let path = dir.join("Cargo.toml");
+    let table = read(&path)
+        .and_then(|mut x| x.remove("package"))
+        .and_then(|x| match x { Value::Table(mut x) => x.remove("metadata"), _ => None })
+        .and_then(|x| match x { Value::Table(mut x) => x.remove("colorthis"), _ => None });
+    match table {
+        Some(Value::Table(table)) => from_table(table, path),
+        Some(_) => abort!(Span::call_site(), "{}: package.metadata.colorthis must be a table.", path.display()),
+        None => Config::default(),
+    }
+}
+
+fn read(path: &Path) -> Option<Table> {
+    let src = std::fs::read_to_string(path).ok()?;
+    match src.parse() {
+        Ok(table) => Some(table),
+        Err(e) => abort!(Span::call_site(), "{}: {}", path.display(), e),
+    }
+}
+
+fn from_table(table: Table, path: PathBuf) -> Config {
+    match parse_config(table) {
+        Ok(mut config) => {
+            config.path = Some(path);
+            config
+        },
+        Err(e) => abort!(Span::call_site(), "{}: {}", path.display(), e),
+    }
+}
+
+fn parse_config(table: Table) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("check", Value::String(s)) => config.check = Some(match s.as_str() {
+                "checked" => Check::Checked,
+                "unchecked" => Check::Unchecked,
+                "clamp" => Check::Clamp,
+                _ => return Err(format!("Expected check to be one of \"checked\", \"unchecked\" or \"clamp\", found {:?}.", s)),
+            }),
+            ("round", Value::String(s)) => config.round = Some(match s.as_str() {
+                "half_even" => Rounding::HalfEven,
+                "half_up" => Rounding::HalfUp,
+                "floor" => Rounding::Floor,
+                "trunc" => Rounding::Trunc,
+                _ => return Err(format!("Expected round to be one of \"half_even\", \"half_up\", \"floor\" or \"trunc\", found {:?}.", s)),
+            }),
+            ("scale", Value::Integer(255)) => config.scale = Some(Scale::S255),
+            ("scale", Value::Integer(256)) => config.scale = Some(Scale::S256),
+            ("gamut", Value::String(s)) => config.strict_gamut = Some(match s.as_str() {
+                "map" => false,
+                "strict" => true,
+                _ => return Err(format!("Expected gamut to be \"map\" or \"strict\", found {:?}.", s)),
+            }),
+            ("alpha", Value::Integer(i)) if (0..=255).contains(&i) => config.alpha = Some(i as f64 / 255.0),
+            ("alpha", Value::Float(f)) if (0.0..=1.0).contains(&f) => config.alpha = Some(f),
+            (key @ ("colors" | "overrides"), Value::Table(colors)) => for (name, value) in colors {
+                let overrides = key == "overrides";
+                match (builtin_color(&name).is_some(), overrides) {
+                    (true, false) => return Err(format!("{} shadows a built-in color, move it to [overrides].", name)),
+                    (false, true) => return Err(format!("{} does not shadow any color, move it to [colors].", name)),
+                    _ => (),
+                }
+                if config.color(&name).is_some() {
+                    return Err(format!("Duplicate color name {}.", name))
+                }
+                match value {
+                    Value::String(s) => config.colors.push((name, s)),
+                    _ => return Err(format!("Expected color {} to be a string.", name)),
+                }
+            },
+            ("check" | "round" | "scale" | "gamut" | "alpha" | "colors" | "overrides", value) => {
+                return Err(format!("Invalid value for {}: {}.", key, value))
+            },
+            _ => return Err(format!("Unknown key {}.", key)),
+        }
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// The configured check, otherwise set by the `unchecked` and `clamp` features.
+    pub fn check(&self) -> Check {
+        match self.check {
+            Some(check) => check,
+            None if cfg!(feature="clamp") => Check::Clamp,
+            None if cfg!(feature="unchecked") => Check::Unchecked,
+            None => Check::Checked,
+        }
+    }
+
+    /// The configured gamut handling, otherwise set by the `strict-gamut` feature.
+    pub fn strict_gamut(&self) -> bool {
+        self.strict_gamut.unwrap_or(cfg!(feature="strict-gamut"))
+    }
+
+    /// Alpha of colors without an alpha channel, in `0..=255`.
+    pub fn alpha_int(&self) -> i64 {
+        self.alpha.map(|a| (a * 255.0).round() as i64).unwrap_or(255)
+    }
+
+    /// Alpha of colors without an alpha channel, in `0.0..=1.0`.
+    pub fn alpha_float(&self) -> f64 {
+        self.alpha.unwrap_or(1.0)
+    }
+
+    /// Look up a custom color name.
+    pub fn color(&self, name: &str) -> Option<&str> {
+        self.colors.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Makes the invoking crate recompile when the configuration changes.
+    pub fn track(&self) -> Option<TokenStream> {
+        let path = self.path.as_ref()?.to_str()?;
+        Some(quote!(const _: &[u8] = include_bytes!(#path);))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_config, Check};
+    use crate::convert::{Rounding, Scale};
+
+    #[test]
+    pub fn test_parse_config() {
+        let config = parse_config(r##"
+            check = "clamp"
+            round = "half_up"
+            scale = 256
+            gamut = "strict"
+            alpha = 128
+
+            [colors]
+            Brand = "#FF8800"
+            Accent = "oklch(0.7 0.1 200)"
+        "##.parse().unwrap()).unwrap();
+        assert_eq!(config.check, Some(Check::Clamp));
+        assert_eq!(config.round, Some(Rounding::HalfUp));
+        assert_eq!(config.scale, Some(Scale::S256));
+        assert_eq!(config.strict_gamut, Some(true));
+        assert_eq!(config.alpha_int(), 128);
+        assert_eq!(config.color("Brand"), Some("#FF8800"));
+        assert_eq!(config.color("Accent"), Some("oklch(0.7 0.1 200)"));
+        assert_eq!(config.color("brand"), None);
+
+        assert!(parse_config("alpha = 2.0".parse().unwrap()).is_err());
+        assert!(parse_config("scale = 100".parse().unwrap()).is_err());
+        assert!(parse_config("colours = {}".parse().unwrap()).is_err());
+        assert!(parse_config("[colors]\nBrand = 1".parse().unwrap()).is_err());
+        assert!(parse_config("[colors]\nBrand = \"#FFFFFF\"\n[overrides]\nBrand = \"#FFFFFF\"".parse().unwrap()).is_err());
+        #[cfg(feature="parse-color")]
+        {
+            assert!(parse_config("[colors]\nRed = \"#FF0000\"".parse().unwrap()).is_err());
+            assert!(parse_config("[overrides]\nRed = \"#FF0000\"".parse().unwrap()).is_ok());
+        }
+        assert!(parse_config("[overrides]\nBrand = \"#FF0000\"".parse().unwrap()).is_err());
+    }
+}
@@ -1,99 +1,516 @@
-use proc_macro2::Literal;
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use crate::parse::ColorResult;
+use colorthis_core::space;
+use crate::config::config;
+use crate::options::channel_order;
+
+/// Suffixed channel type chosen by the `ty` option.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ChannelType {
+    U8,
+    U16,
+    /// Normalized to `0..=127`.
+    I8,
+    /// Normalized to `0..=32767`.
+    I16,
+    F32,
+    F64,
+}
+
+impl ChannelType {
+    pub fn is_float(self) -> bool {
+        matches!(self, ChannelType::F32 | ChannelType::F64)
+    }
+
+    /// Value of `1.0` in an integer type.
+    fn max(self) -> i64 {
+        match self {
+            ChannelType::U8 => 255,
+            ChannelType::U16 => 65535,
+            ChannelType::I8 => 127,
+            ChannelType::I16 => 32767,
+            ChannelType::F32 | ChannelType::F64 => 1,
+        }
+    }
+
+    /// Literal of a value already clamped to `0..=max`.
+    fn scaled(self, x: i64) -> Literal {
+        match self {
+            ChannelType::U8 => Literal::u8_suffixed(x as u8),
+            ChannelType::U16 => Literal::u16_suffixed(x as u16),
+            ChannelType::I8 => Literal::i8_suffixed(x as i8),
+            ChannelType::I16 => Literal::i16_suffixed(x as i16),
+            ChannelType::F32 | ChannelType::F64 => Literal::i64_unsuffixed(x),
+        }
+    }
+}
+
+/// Rounding used when quantizing floats to integers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Rounding {
+    HalfEven,
+    HalfUp,
+    Floor,
+    Trunc,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        if cfg!(feature="round-half-even") {
+            Rounding::HalfEven
+        } else if cfg!(feature="round-half-up") {
+            Rounding::HalfUp
+        } else if cfg!(feature="round-floor") {
+            Rounding::Floor
+        } else {
+            Rounding::Trunc
+        }
+    }
+}
+
+impl Rounding {
+    fn apply(self, x: f64) -> f64 {
+        match self {
+            Rounding::HalfEven => x.round_ties_even(),
+            Rounding::HalfUp => (x + 0.5).floor(),
+            Rounding::Floor => x.floor(),
+            Rounding::Trunc => x.trunc(),
+        }
+    }
+}
+
+/// Whether `1.0` maps to `max` or `max + 1` (clamped to `max`) when quantizing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Scale {
+    S255,
+    S256,
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        if cfg!(feature="scale-256") {
+            Scale::S256
+        } else {
+            Scale::S255
+        }
+    }
+}
+
+/// How channels are written as literals.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Format {
+    pub ty: Option<ChannelType>,
+    pub round: Rounding,
+    /// Rounding of premultiplied integers, to the nearest unless `round` is set.
+    pub premul_round: Rounding,
+    pub scale: Scale,
+}
+
+/// Tolerance for rounding errors in quantized values.
+const QUANTIZE_EPSILON: f64 = 1e-4;
+
+impl Format {
+    /// Limit a quantized value to `0..=max`. With an integer `ty` out of range values from `unchecked`
+    /// are clamped, untyped literals pass them through.
+    fn limit(self, x: i64, in_range: bool, max: i64) -> i64 {
+        match self.ty {
+            Some(ty) if !ty.is_float() => x.clamp(0, max),
+            _ if in_range => x.min(max),
+            _ => x,
+        }
+    }
+
+    /// Quantize a value in `0.0..=1.0` to `0..=max`.
+    pub fn quantize(self, x: f64, max: i64) -> i64 {
+        let scaled = match self.scale {
+            Scale::S255 => x * max as f64,
+            Scale::S256 => x * (max + 1) as f64,
+        };
+        // Conversions between color spaces are off by rounding errors like `188.99999`.
+        let scaled = match scaled.round() {
+            near if (scaled - near).abs() <= QUANTIZE_EPSILON => near,
+            _ => scaled,
+        };
+        self.limit(self.round.apply(scaled) as i64, (0.0..=1.0).contains(&x), max)
+    }
+
+    /// Rescale a value in `0..=255` to `0..=max`.
+    pub fn rescale(self, x: i64, max: i64) -> i64 {
+        if max == 255 {
+            return self.limit(x, (0..=255).contains(&x), max)
+        }
+        // exact when divisible, and never exactly half way since 255 is odd.
+        let scaled = match self.scale {
+            Scale::S255 => (x * max) as f64 / 255.0,
+            Scale::S256 => (x * (max + 1)) as f64 / 256.0,
+        };
+        // Conversions between color spaces are off by rounding errors like `188.99999`.
+        let scaled = match scaled.round() {
+            near if (scaled - near).abs() <= QUANTIZE_EPSILON => near,
+            _ => scaled,
+        };
+        self.limit(self.round.apply(scaled) as i64, (0..=255).contains(&x), max)
+    }
+
+    /// `u32` expression of `x * max / 255` for integers in `0..=255`, rounded like [`Format::rescale`] at runtime.
+    pub fn rescale_expr(self, x: TokenStream, max: &TokenStream) -> TokenStream {
+        let factor = match self.scale {
+            Scale::S255 => quote!(m),
+            Scale::S256 => quote!((m + 1)),
+        };
+        let divisor = match self.scale {
+            Scale::S255 => quote!(255),
+            Scale::S256 => quote!(256),
+        };
+        // `x * max / 255` is never exactly half way, `x * (max + 1) / 256` can be.
+        let bias = match (self.round, self.scale) {
+            (Rounding::Trunc | Rounding::Floor, _) => quote!(0),
+            (Rounding::HalfUp | Rounding::HalfEven, Scale::S255) => quote!(127),
+            (Rounding::HalfUp, Scale::S256) => quote!(128),
+            (Rounding::HalfEven, Scale::S256) => quote!(127 + ((p >> 8) & 1)),
+        };
+        // Both expressions are evaluated before the locals are in scope.
+        quote!({
+            let (x, m) = ((#x) as u32, (#max) as u32);
+            let p = x * #factor;
+            ((p + #bias) / #divisor).min(m)
+        })
+    }
+
+    /// Integer literal from a value in `0..=255`, scaled to the range of `ty`.
+    fn int(self, x: i64) -> Literal {
+        match self.ty {
+            None | Some(ChannelType::F32 | ChannelType::F64) => Literal::i64_unsuffixed(x),
+            Some(ty) => ty.scaled(self.rescale(x, ty.max())),
+        }
+    }
+
+    /// Integer literal from a value in `0.0..=1.0`, scaled to the range of `ty`.
+    fn float_to_int(self, x: f64) -> Literal {
+        match self.ty {
+            None | Some(ChannelType::F32 | ChannelType::F64) => Literal::i64_unsuffixed(self.quantize(x, 255)),
+            Some(ty) => ty.scaled(self.quantize(x, ty.max())),
+        }
+    }
+
+    /// Float literal, suffixed if `ty` is a float type.
+    fn float(self, x: f64) -> Literal {
+        match self.ty {
+            Some(ChannelType::F32) => Literal::f32_suffixed(x as f32),
+            Some(ChannelType::F64) => Literal::f64_suffixed(x),
+            _ => Literal::f64_unsuffixed(x),
+        }
+    }
+}
 
 pub(crate) trait Convert {
-    fn to_int3(&self) -> [Literal; 3];
-    fn to_int4(&self) -> [Literal; 4];
-    fn to_float3(&self) -> [Literal; 3];
-    fn to_float4(&self) -> [Literal; 4];
+    fn to_int3(&self, format: Format) -> [Literal; 3];
+    fn to_int4(&self, format: Format) -> [Literal; 4];
+    fn to_float3(&self, format: Format) -> [Literal; 3];
+    fn to_float4(&self, format: Format) -> [Literal; 4];
+    /// Channels in `0.0..=1.0`, following the same rules as `to_float4`.
+    fn normalize(&self) -> [f64; 4];
 }
 
 
 impl Convert for [i64; 4] {
-    fn to_int3(&self) -> [Literal; 3] {
+    fn to_int3(&self, format: Format) -> [Literal; 3] {
         [
-            Literal::i64_unsuffixed(self[0]),
-            Literal::i64_unsuffixed(self[1]),
-            Literal::i64_unsuffixed(self[2]),
+            format.int(self[0]),
+            format.int(self[1]),
+            format.int(self[2]),
         ]
     }
 
-    fn to_int4(&self) -> [Literal; 4] {
+    fn to_int4(&self, format: Format) -> [Literal; 4] {
         [
-            Literal::i64_unsuffixed(self[0]),
-            Literal::i64_unsuffixed(self[1]),
-            Literal::i64_unsuffixed(self[2]),
-            Literal::i64_unsuffixed(self[3]),
+            format.int(self[0]),
+            format.int(self[1]),
+            format.int(self[2]),
+            format.int(self[3]),
         ]
     }
 
-    fn to_float3(&self) -> [Literal; 3] {
+    fn to_float3(&self, format: Format) -> [Literal; 3] {
         if self[0..3].iter().all(|x|(0..=1).contains(x)){
             [
-                Literal::f64_unsuffixed(self[0] as f64),
-                Literal::f64_unsuffixed(self[1] as f64),
-                Literal::f64_unsuffixed(self[2] as f64),
+                format.float(self[0] as f64),
+                format.float(self[1] as f64),
+                format.float(self[2] as f64),
             ]
         } else {
             [
-                Literal::f64_unsuffixed(self[0] as f64 / 255.0),
-                Literal::f64_unsuffixed(self[1] as f64 / 255.0),
-                Literal::f64_unsuffixed(self[2] as f64 / 255.0),
+                format.float(self[0] as f64 / 255.0),
+                format.float(self[1] as f64 / 255.0),
+                format.float(self[2] as f64 / 255.0),
             ]
         }
     }
 
-    fn to_float4(&self) -> [Literal; 4] {
+    fn to_float4(&self, format: Format) -> [Literal; 4] {
+        self.normalize().map(|x| format.float(x))
+    }
+
+    fn normalize(&self) -> [f64; 4] {
         if self.iter().all(|x|(0..=1).contains(x)){
-            [
-                Literal::f64_unsuffixed(self[0] as f64),
-                Literal::f64_unsuffixed(self[1] as f64),
-                Literal::f64_unsuffixed(self[2] as f64),
-                Literal::f64_unsuffixed(self[3] as f64),
-            ]
+            self.map(|x| x as f64)
         } else {
-            [
-                Literal::f64_unsuffixed(self[0] as f64 / 255.0),
-                Literal::f64_unsuffixed(self[1] as f64 / 255.0),
-                Literal::f64_unsuffixed(self[2] as f64 / 255.0),
-                Literal::f64_unsuffixed(self[3] as f64 / 255.0),
-            ]
+            self.map(|x| x as f64 / 255.0)
         }
     }
 }
 
 
 impl Convert for [f64; 4] {
-    fn to_int3(&self) -> [Literal; 3] {
+    fn to_int3(&self, format: Format) -> [Literal; 3] {
         [
-            Literal::i64_unsuffixed((self[0] * 255.0) as i64),
-            Literal::i64_unsuffixed((self[1] * 255.0) as i64),
-            Literal::i64_unsuffixed((self[2] * 255.0) as i64),
+            format.float_to_int(self[0]),
+            format.float_to_int(self[1]),
+            format.float_to_int(self[2]),
         ]
     }
 
-    fn to_int4(&self) -> [Literal; 4] {
-        [
-            Literal::i64_unsuffixed((self[0] * 255.0) as i64),
-            Literal::i64_unsuffixed((self[1] * 255.0) as i64),
-            Literal::i64_unsuffixed((self[2] * 255.0) as i64),
-            Literal::i64_unsuffixed((self[3] * 255.0) as i64),
-        ]
+    fn to_int4(&self, format: Format) -> [Literal; 4] {
+        self.map(|x| format.float_to_int(x))
     }
 
-    fn to_float3(&self) -> [Literal; 3] {
+    fn to_float3(&self, format: Format) -> [Literal; 3] {
         [
-            Literal::f64_unsuffixed(self[0]),
-            Literal::f64_unsuffixed(self[1]),
-            Literal::f64_unsuffixed(self[2]),
+            format.float(self[0]),
+            format.float(self[1]),
+            format.float(self[2]),
         ]
     }
 
-    fn to_float4(&self) -> [Literal; 4] {
-        [
-            Literal::f64_unsuffixed(self[0]),
-            Literal::f64_unsuffixed(self[1]),
-            Literal::f64_unsuffixed(self[2]),
-            Literal::f64_unsuffixed(self[3]),
-        ]
+    fn to_float4(&self, format: Format) -> [Literal; 4] {
+        self.map(|x| format.float(x))
+    }
+
+    fn normalize(&self) -> [f64; 4] {
+        *self
+    }
+}
+
+/// Channels produced by a macro.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Output {
+    /// 3 integers in `0..=255`.
+    Int3,
+    /// 4 integers in `0..=255`.
+    Int4,
+    /// 3 floats in `0.0..=1.0`.
+    Float3,
+    /// 4 floats in `0.0..=1.0`.
+    Float4,
+    /// Hue in degrees, saturation, lightness and alpha.
+    Hsla,
+    /// Hue in degrees, saturation, value and alpha.
+    Hsva,
+    /// OKLCH lightness, chroma, hue in degrees and alpha.
+    Oklcha,
+    /// CIE Lab lightness in `0..=100`, a, b and alpha.
+    Laba,
+    /// CIE LCH lightness in `0..=100`, chroma, hue in degrees and alpha.
+    Lcha,
+    /// 3 linear floats in `0.0..=1.0`.
+    Linear3,
+    /// 4 linear floats in `0.0..=1.0`, alpha is not affected.
+    Linear4,
+    /// 4 integers in `0..=255`, with RGB multiplied by alpha.
+    PremulInt4,
+    /// 4 floats in `0.0..=1.0`, with RGB multiplied by alpha.
+    PremulFloat4,
+}
+
+impl Output {
+    /// Output of a macro by name, like `rgbaf`.
+    pub fn from_name(name: &str) -> Option<Output> {
+        Some(match name {
+            "rgb" => Output::Int3,
+            "rgba" => Output::Int4,
+            "rgbf" => Output::Float3,
+            "rgbaf" => Output::Float4,
+            "hsla" => Output::Hsla,
+            "hsva" => Output::Hsva,
+            "oklcha" => Output::Oklcha,
+            "laba" => Output::Laba,
+            "lcha" => Output::Lcha,
+            "rgb_linear" => Output::Linear3,
+            "rgba_linear" => Output::Linear4,
+            "rgba_premul" => Output::PremulInt4,
+            "rgbaf_premul" => Output::PremulFloat4,
+            _ => return None,
+        })
+    }
+
+    /// Number of channels produced.
+    pub fn channels(self) -> usize {
+        match self {
+            Output::Int3 | Output::Float3 | Output::Linear3 => 3,
+            _ => 4,
+        }
+    }
+
+    /// Name of the color space, used in error messages.
+    fn name(self) -> &'static str {
+        match self {
+            Output::Int3 | Output::Int4 | Output::Float3 | Output::Float4 => "RGB",
+            Output::PremulInt4 | Output::PremulFloat4 => "premultiplied RGB",
+            Output::Hsla => "HSL",
+            Output::Hsva => "HSV",
+            Output::Oklcha => "OKLCH",
+            Output::Laba => "CIE Lab",
+            Output::Lcha => "CIE LCH",
+            Output::Linear3 | Output::Linear4 => "linear RGB",
+        }
+    }
+
+    /// Channel names used by the `order` option, CIE Lab has none since `a` is ambiguous.
+    fn letters(self) -> Option<&'static str> {
+        match self {
+            Output::Int3 | Output::Float3 | Output::Linear3 => Some("rgb"),
+            Output::Int4 | Output::Float4 | Output::Linear4
+                | Output::PremulInt4 | Output::PremulFloat4 => Some("rgba"),
+            Output::Hsla => Some("hsla"),
+            Output::Hsva => Some("hsva"),
+            Output::Oklcha | Output::Lcha => Some("lcha"),
+            Output::Laba => None,
+        }
+    }
+
+    /// Reorder channels by an `order` option like `bgra`.
+    pub fn reorder(self, items: Vec<TokenStream>, order: &Ident) -> Vec<TokenStream> {
+        let letters = match self.letters() {
+            Some(letters) => letters,
+            None => abort!(order.span(), "Channel order is not supported for {}.", self.name()),
+        };
+        channel_order(order, letters).into_iter().map(|i| items[i].clone()).collect()
+    }
+
+    fn literals(self, color: &impl Convert, format: Format) -> Vec<Literal> {
+        let float = format.ty.map(ChannelType::is_float);
+        let [r, g, b, a] = color.normalize();
+        let [x, y, z] = match (self, float) {
+            (Output::Int3, Some(true)) | (Output::Float3, None | Some(true)) => return color.to_float3(format).into(),
+            (Output::Int3, _) | (Output::Float3, _) => return color.to_int3(format).into(),
+            (Output::Int4 | Output::PremulInt4, Some(true))
+                | (Output::Float4 | Output::PremulFloat4, None | Some(true)) => return color.to_float4(format).into(),
+            (Output::Int4 | Output::PremulInt4, _)
+                | (Output::Float4 | Output::PremulFloat4, _) => return color.to_int4(format).into(),
+            (Output::Linear3 | Output::Linear4, _) => [r, g, b].map(space::srgb_decode),
+            (_, Some(false)) => abort!(Span::call_site(), "{} cannot be converted to integers.", self.name()),
+            (Output::Hsla, _) => space::srgb_to_hsl([r, g, b]),
+            (Output::Hsva, _) => space::srgb_to_hsv([r, g, b]),
+            (Output::Oklcha, _) => space::srgb_to_oklch([r, g, b]),
+            (Output::Laba, _) => space::srgb_to_lab([r, g, b]),
+            (Output::Lcha, _) => space::lab_to_lch(space::srgb_to_lab([r, g, b])),
+        };
+        let values = [x, y, z, a];
+        if float == Some(false) {
+            // only linear RGB gets here.
+            return values.to_int4(format).into_iter().take(self.channels()).collect()
+        }
+        values.map(|x| format.float(x)).into_iter().take(self.channels()).collect()
+    }
+
+    /// Multiply RGB by alpha if this output is premultiplied.
+    ///
+    /// Integers are rounded with the rounding and scale of `format`, floats are kept
+    /// and quantized like any other float. `convert` sets the rounding of premultiplied integers.
+    fn premultiply(self, color: ColorResult, format: Format) -> ColorResult {
+        match (self, color) {
+            (Output::PremulInt4, ColorResult::Integers([r, g, b, a])) => {
+                let mul = |x: i64| format.rescale(x, a);
+                ColorResult::Integers([mul(r), mul(g), mul(b), a])
+            },
+            (Output::PremulInt4 | Output::PremulFloat4, ColorResult::Floats([r, g, b, a])) => {
+                ColorResult::Floats([r * a, g * a, b * a, a])
+            },
+            (Output::PremulFloat4, ColorResult::Integers(ints)) => {
+                let [r, g, b, a] = ints.normalize();
+                ColorResult::Floats([r * a, g * a, b * a, a])
+            },
+            // Expressions without alpha are multiplied by the configured alpha.
+            (Output::PremulInt4, ColorResult::Exprs([r, g, b, a])) => {
+                let a = a.unwrap_or_else(|| Literal::i64_unsuffixed(config().alpha_int()).into_token_stream());
+                let mul = |x: Option<TokenStream>| x.map(|x| {
+                    let x = format.rescale_expr(x, &a);
+                    quote!(#x as _)
+                });
+                ColorResult::Exprs([mul(r), mul(g), mul(b), Some(a)])
+            },
+            (Output::PremulFloat4, ColorResult::Exprs([r, g, b, a])) => {
+                let a = a.unwrap_or_else(|| Literal::f64_unsuffixed(config().alpha_float()).into_token_stream());
+                let mul = |x: Option<TokenStream>| x.map(|x| quote!((#x) * (#a)));
+                ColorResult::Exprs([mul(r), mul(g), mul(b), Some(a)])
+            },
+            (_, color) => color,
+        }
+    }
+
+    /// Convert a parsed color into the channels of this output.
+    pub fn convert(self, color: ColorResult, format: Format) -> Vec<TokenStream> {
+        let format = match self {
+            Output::PremulInt4 => Format { round: format.premul_round, ..format },
+            _ => format,
+        };
+        let literals = match self.premultiply(color, format) {
+            ColorResult::Integers(ints) => self.literals(&ints, format),
+            ColorResult::Floats(floats) => self.literals(&floats, format),
+            ColorResult::Exprs(_) if format.ty.is_some() => {
+                abort!(Span::call_site(), "Expressions cannot be converted to a channel type at compile time.")
+            },
+            ColorResult::Exprs(streams) => {
+                let alpha = match self {
+                    Output::Int3 | Output::Int4 | Output::PremulInt4 => Literal::i64_unsuffixed(config().alpha_int()),
+                    Output::Float3 | Output::Float4 | Output::PremulFloat4 => Literal::f64_unsuffixed(config().alpha_float()),
+                    _ => abort!(Span::call_site(), "Expressions cannot be converted to {} at compile time.", self.name()),
+                };
+                return streams.into_iter()
+                    .take(self.channels())
+                    .map(|x| x.unwrap_or_else(|| alpha.to_token_stream()))
+                    .collect();
+            },
+        };
+        literals.into_iter().map(|x| x.into_token_stream()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_out_of_range() {
+        let format = Format { ty: None, round: Rounding::Trunc, premul_round: Rounding::Trunc, scale: Scale::S255 };
+        assert_eq!(format.rescale(300, 255), 300);
+        assert_eq!(format.quantize(-0.5, 255), -127);
+        assert_eq!(Format { scale: Scale::S256, ..format }.quantize(1.0, 255), 255);
+        let format = Format { ty: Some(ChannelType::U8), ..format };
+        assert_eq!(format.rescale(300, 255), 255);
+        assert_eq!(format.rescale(-1, 255), 0);
+        assert_eq!(format.rescale(300, 65535), 65535);
+        assert_eq!(format.rescale(-1, 127), 0);
+        assert_eq!(format.quantize(1.5, 255), 255);
+        assert_eq!(format.quantize(-0.5, 255), 0);
+        assert_eq!(format.int(300).to_string(), "255u8");
+        assert_eq!(format.float_to_int(-1.0).to_string(), "0u8");
+    }
+
+    #[test]
+    pub fn test_premultiply_default_alpha() {
+        let format = Format { ty: None, round: Rounding::Trunc, premul_round: Rounding::Trunc, scale: Scale::S255 };
+        let exprs = || ColorResult::Exprs([Some(quote!(r)), Some(quote!(g)), Some(quote!(b)), None]);
+        let ColorResult::Exprs([Some(r), _, _, Some(a)]) = Output::PremulInt4.premultiply(exprs(), format) else {
+            panic!("expected expressions with alpha")
+        };
+        assert_eq!(a.to_string(), config().alpha_int().to_string());
+        assert!(r.to_string().contains(&a.to_string()));
+        let ColorResult::Exprs([Some(r), _, _, Some(a)]) = Output::PremulFloat4.premultiply(exprs(), format) else {
+            panic!("expected expressions with alpha")
+        };
+        assert_eq!(r.to_string(), quote!((r) * (#a)).to_string());
     }
 }
@@ -0,0 +1,401 @@
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
+use proc_macro_error::abort;
+use quote::{format_ident, quote};
+use crate::convert::{ChannelType, Format, Output};
+use crate::parse::ColorResult;
+
+/// Options from `#[colorthis(macro = name, linear, premul)]`.
+#[derive(Default)]
+struct Attribute {
+    found: bool,
+    name: Option<Ident>,
+    linear: bool,
+    premul: bool,
+}
+
+/// A struct or an enum variant with its fields.
+struct Fields {
+    /// `Some` for named fields, the name of each field.
+    names: Option<Vec<Ident>>,
+    types: Vec<String>,
+    span: Span,
+}
+
+/// Convert a type name like `RgbaLinear` to `rgba_linear`.
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && prev_lower {
+            result.push('_');
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+/// Read attributes at the front of `tokens`, returning the `colorthis` attribute.
+fn attributes(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) -> Attribute {
+    let mut result = Attribute::default();
+    while matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#') {
+        tokens.next();
+        let Some(TokenTree::Group(group)) = tokens.next() else {
+            abort!(Span::call_site(), "Expected attribute.")
+        };
+        let mut iter = group.stream().into_iter();
+        match (iter.next(), iter.next()) {
+            (Some(TokenTree::Ident(name)), None) if name == "colorthis" => result.found = true,
+            (Some(TokenTree::Ident(name)), Some(TokenTree::Group(args))) if name == "colorthis" => {
+                result.found = true;
+                attribute_args(&mut result, args.stream());
+            },
+            _ => (),
+        }
+    }
+    result
+}
+
+fn attribute_args(attr: &mut Attribute, tokens: TokenStream) {
+    let mut iter = tokens.into_iter();
+    while let Some(tt) = iter.next() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == ',' => (),
+            TokenTree::Ident(key) if key == "linear" => attr.linear = true,
+            TokenTree::Ident(key) if key == "premul" => attr.premul = true,
+            TokenTree::Ident(key) if key == "macro" => match (iter.next(), iter.next()) {
+                (Some(TokenTree::Punct(p)), Some(TokenTree::Ident(name))) if p.as_char() == '=' => {
+                    attr.name = Some(name)
+                },
+                _ => abort!(key.span(), "Expected `macro = name`."),
+            },
+            tt => abort!(tt.span(), "Unknown attribute {}, expected one of `macro = name`, `linear` or `premul`.", tt),
+        }
+    }
+}
+
+/// Skip a visibility like `pub` or `pub(crate)`.
+fn visibility(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) {
+    if matches!(tokens.peek(), Some(TokenTree::Ident(i)) if i == "pub") {
+        tokens.next();
+        if matches!(tokens.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis) {
+            tokens.next();
+        }
+    }
+}
+
+/// Separate fields by commas outside of angle brackets.
+fn split_fields(tokens: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut result = vec![Vec::new()];
+    let mut depth = 0usize;
+    for tt in tokens {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ',' && depth == 0 => {
+                result.push(Vec::new());
+                continue
+            },
+            TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+            TokenTree::Punct(p) if p.as_char() == '>' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        result.last_mut().unwrap().push(tt);
+    }
+    result.retain(|x| !x.is_empty());
+    result
+}
+
+/// Parse the fields of a struct or a variant from its group, `None` for unit structs.
+fn fields(group: Option<&TokenTree>, span: Span) -> Fields {
+    let (named, stream) = match group {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => (true, g.stream()),
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => (false, g.stream()),
+        _ => abort!(span, "ColorThis requires fields."),
+    };
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for field in split_fields(stream) {
+        let mut iter = field.into_iter().peekable();
+        attributes(&mut iter);
+        visibility(&mut iter);
+        if named {
+            match (iter.next(), iter.next()) {
+                (Some(TokenTree::Ident(name)), Some(TokenTree::Punct(p))) if p.as_char() == ':' => names.push(name),
+                _ => abort!(span, "Expected a field."),
+            }
+        }
+        types.push(iter.map(|x| x.to_string()).collect::<String>());
+    }
+    Fields { names: named.then_some(names), types, span }
+}
+
+/// Channel of a field name, `0..4` for `r, g, b, a` or `red, green, blue, alpha`.
+fn channel(name: &Ident) -> Option<usize> {
+    match name.to_string().to_ascii_lowercase().as_str() {
+        "r" | "red" => Some(0),
+        "g" | "green" => Some(1),
+        "b" | "blue" => Some(2),
+        "a" | "alpha" => Some(3),
+        _ => None,
+    }
+}
+
+/// How a color type stores its channels.
+struct Layout {
+    /// Fields of `r, g, b, a` for named fields.
+    idents: Option<Vec<Ident>>,
+    /// Named fields other than the channels, filled by `Default`.
+    rest: bool,
+    ty: String,
+    alpha: bool,
+    float: bool,
+    span: Span,
+}
+
+impl Layout {
+    fn new(fields: Fields) -> Layout {
+        let span = fields.span;
+        let (types, idents, rest) = match fields.names {
+            Some(names) => {
+                let mut channels: [Option<(String, Ident)>; 4] = Default::default();
+                let mut rest = false;
+                for (name, ty) in names.into_iter().zip(fields.types) {
+                    match channel(&name) {
+                        Some(i) if channels[i].is_some() => abort!(name.span(), "Duplicated channel {}.", name),
+                        Some(i) => channels[i] = Some((ty, name)),
+                        None => rest = true,
+                    }
+                }
+                let [Some(r), Some(g), Some(b), a] = channels else {
+                    abort!(span, "ColorThis requires fields r, g, b and optionally a, or red, green, blue and optionally alpha.")
+                };
+                let (types, idents) = [Some(r), Some(g), Some(b), a].into_iter().flatten().unzip();
+                (types, Some(idents), rest)
+            },
+            None if matches!(fields.types.len(), 3 | 4) => (fields.types, None, false),
+            None => abort!(span, "ColorThis requires 3 or 4 fields, found {}.", fields.types.len()),
+        };
+        let ty = types[0].clone();
+        if types.iter().any(|x| x != &ty) {
+            abort!(span, "Channels of ColorThis must have the same type, found {}.", types.join(", "))
+        }
+        if !matches!(ty.as_str(), "u8" | "u16" | "i8" | "i16" | "f32" | "f64") {
+            abort!(span, "Unsupported channel type {}, expected one of u8, u16, i8, i16, f32 or f64.", ty)
+        }
+        Layout {
+            alpha: types.len() == 4,
+            float: matches!(ty.as_str(), "f32" | "f64"),
+            idents,
+            rest,
+            ty,
+            span,
+        }
+    }
+
+    /// Check the `linear` and `premul` options against the channels.
+    fn check(&self, attr: &Attribute) {
+        match (attr.linear, attr.premul) {
+            (true, true) => abort!(self.span, "`linear` and `premul` cannot be used together."),
+            (true, _) if !self.float => abort!(self.span, "`linear` requires f32 or f64 channels."),
+            (_, true) if !self.alpha => abort!(self.span, "`premul` requires an alpha channel."),
+            _ => (),
+        }
+    }
+}
+
+/// Generate a wrapper macro constructing `path` from the fields.
+fn wrapper(name: Ident, path: TokenStream, layout: Layout, attr: &Attribute) -> TokenStream {
+    layout.check(attr);
+    let mapping = layout.idents.as_ref().map(|idents| {
+        let rest = layout.rest.then(|| quote!(, _));
+        quote!(=> {#(#idents),* #rest})
+    });
+    let option = match layout.ty.as_str() {
+        "u16" | "i8" | "i16" => {
+            let ty = Ident::new(&layout.ty, layout.span);
+            Some(quote!(, ty = #ty))
+        },
+        _ => None,
+    };
+    let colorthis = match (attr.linear, attr.premul, layout.float, layout.alpha) {
+        (true, _, _, false) => "rgb_linear",
+        (true, _, _, true) => "rgba_linear",
+        (_, true, false, _) => "rgba_premul",
+        (_, true, true, _) => "rgbaf_premul",
+        (_, _, false, false) => "rgb",
+        (_, _, false, true) => "rgba",
+        (_, _, true, false) => "rgbf",
+        (_, _, true, true) => "rgbaf",
+    };
+    let colorthis = format_ident!("{}", colorthis);
+    quote! {
+        #[allow(unused_macros)]
+        macro_rules! #name {
+            ($first: literal, $($rest: literal),+ $(,)?) => {
+                ::colorthis::#colorthis!(#path, [$first, $($rest),+] #mapping #option)
+            };
+            ($($color: tt)+) => {
+                ::colorthis::#colorthis!(#path, $($color)+ #mapping #option)
+            };
+        }
+    }
+}
+
+/// Generate `from_color_this` constructing `path` from the fields, and `impl FromColorThis`.
+fn from_color_this(name: &Ident, path: TokenStream, layout: Layout, attr: &Attribute) -> TokenStream {
+    layout.check(attr);
+    if attr.premul && !matches!(layout.ty.as_str(), "u8" | "f32" | "f64") {
+        abort!(layout.span, "`premul` requires u8, f32 or f64 channels for FromColorThis.")
+    }
+    let field = match (attr.linear, attr.premul) {
+        (true, _) => format_ident!("linear_{}", layout.ty),
+        (_, true) => format_ident!("premul_{}", layout.ty),
+        _ => format_ident!("{}", layout.ty),
+    };
+    let items = (0..3 + layout.alpha as usize).map(|i| quote!(color.#field[#i]));
+    let value = match &layout.idents {
+        Some(idents) if layout.rest => quote!(#path {#(#idents: #items),*, ..Default::default()}),
+        Some(idents) => quote!(#path {#(#idents: #items),*}),
+        None => quote!(#path (#(#items),*)),
+    };
+    // `Default::default()` cannot be called in a `const fn`.
+    let constness = (!layout.rest).then(|| quote!(const));
+    quote! {
+        impl #name {
+            /// Construct from the channels built by `colorthis::color!`.
+            pub #constness fn from_color_this(color: ::colorthis_core::Channels) -> Self {
+                #value
+            }
+        }
+
+        impl ::colorthis_core::FromColorThis for #name {
+            fn from_color_this(color: ::colorthis_core::Channels) -> Self {
+                #name::from_color_this(color)
+            }
+        }
+    }
+}
+
+/// A struct, or the variants of an enum marked `#[colorthis]`.
+struct Target {
+    /// Name of the struct or the variant.
+    name: Ident,
+    path: TokenStream,
+    layout: Layout,
+    attr: Attribute,
+}
+
+/// Find the types to generate code for, returns the name of the type and its targets.
+fn targets(tokens: TokenStream) -> (Ident, Vec<Target>) {
+    let mut iter = tokens.into_iter().peekable();
+    let attr = attributes(&mut iter);
+    visibility(&mut iter);
+    let (kind, name) = match (iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(kind)), Some(TokenTree::Ident(name))) => (kind, name),
+        _ => abort!(Span::call_site(), "Expected a struct or an enum."),
+    };
+    if let Some(TokenTree::Punct(p)) = iter.peek() {
+        if p.as_char() == '<' {
+            abort!(p.span(), "ColorThis does not support generics.")
+        }
+    }
+    let body = iter.next();
+    match kind.to_string().as_str() {
+        "struct" => {
+            let layout = Layout::new(fields(body.as_ref(), name.span()));
+            let target = Target { name: name.clone(), path: quote!(#name), layout, attr };
+            (name, vec![target])
+        },
+        "enum" => {
+            let Some(TokenTree::Group(group)) = body else {
+                abort!(name.span(), "Expected enum variants.")
+            };
+            let mut result = Vec::new();
+            for variant in split_fields(group.stream()) {
+                let mut iter = variant.into_iter().peekable();
+                let attr = attributes(&mut iter);
+                let Some(TokenTree::Ident(variant)) = iter.next() else {
+                    abort!(group.span(), "Expected an enum variant.")
+                };
+                if !attr.found {
+                    continue
+                }
+                let layout = Layout::new(fields(iter.next().as_ref(), variant.span()));
+                result.push(Target { path: quote!(#name::#variant), name: variant, layout, attr });
+            }
+            if result.is_empty() {
+                abort!(name.span(), "Mark enum variants with #[colorthis] to generate their macros.")
+            }
+            (name, result)
+        },
+        _ => abort!(kind.span(), "ColorThis only supports structs and enums."),
+    }
+}
+
+/// Expand `#[derive(ColorThis)]` into wrapper macros.
+pub(crate) fn derive(tokens: TokenStream) -> TokenStream {
+    let (_, targets) = targets(tokens);
+    targets.into_iter().map(|target| {
+        let macro_name = target.attr.name.clone()
+            .unwrap_or_else(|| Ident::new(&snake_case(&target.name.to_string()), target.name.span()));
+        wrapper(macro_name, target.path, target.layout, &target.attr)
+    }).collect()
+}
+
+/// Expand `#[derive(FromColorThis)]`, enums need exactly one variant marked `#[colorthis]`.
+pub(crate) fn derive_from(tokens: TokenStream) -> TokenStream {
+    let (name, mut targets) = targets(tokens);
+    if targets.len() > 1 {
+        abort!(name.span(), "FromColorThis requires exactly one variant marked #[colorthis], found {}.", targets.len())
+    }
+    let target = targets.remove(0);
+    from_color_this(&name, target.path, target.layout, &target.attr)
+}
+
+/// Construct `path::from_color_this` with the color in every channel type.
+pub(crate) fn channels(path: TokenStream, color: ColorResult, format: Format) -> TokenStream {
+    let convert = |output: Output, ty: ChannelType| {
+        let items = output.convert(color.clone(), Format { ty: Some(ty), ..format });
+        quote!([#(#items),*])
+    };
+    let u8 = convert(Output::Int4, ChannelType::U8);
+    let u16 = convert(Output::Int4, ChannelType::U16);
+    let i8 = convert(Output::Int4, ChannelType::I8);
+    let i16 = convert(Output::Int4, ChannelType::I16);
+    let f32 = convert(Output::Float4, ChannelType::F32);
+    let f64 = convert(Output::Float4, ChannelType::F64);
+    let linear_f32 = convert(Output::Linear4, ChannelType::F32);
+    let linear_f64 = convert(Output::Linear4, ChannelType::F64);
+    let premul_u8 = convert(Output::PremulInt4, ChannelType::U8);
+    let premul_f32 = convert(Output::PremulFloat4, ChannelType::F32);
+    let premul_f64 = convert(Output::PremulFloat4, ChannelType::F64);
+    // The inherent `const fn` of derived types takes priority over the trait.
+    quote! {{
+        use ::colorthis_core::FromColorThis as _;
+        <#path>::from_color_this(::colorthis_core::Channels::__new(
+            #u8,
+            #u16,
+            #i8,
+            #i16,
+            #f32,
+            #f64,
+            #linear_f32,
+            #linear_f64,
+            #premul_u8,
+            #premul_f32,
+            #premul_f64,
+        ))
+    }}
+}
+
+#[cfg(test)]
+mod test {
+    use super::snake_case;
+
+    #[test]
+    pub fn test_snake_case() {
+        assert_eq!(snake_case("Color"), "color");
+        assert_eq!(snake_case("RgbaLinear"), "rgba_linear");
+        assert_eq!(snake_case("RGBA8"), "rgba8");
+        assert_eq!(snake_case("Srgb8Color"), "srgb8_color");
+    }
+}
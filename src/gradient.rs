@@ -0,0 +1,37 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use proc_macro_error::abort;
+use crate::parse::ColorResult;
+
+pub(crate) use colorthis_core::gradient::{gradient, positions, HueMethod, Space};
+
+/// A color as `0.0..=1.0` sRGB floats, expressions are not supported.
+pub(crate) fn floats(color: ColorResult, span: Span) -> [f64; 4] {
+    match color {
+        ColorResult::Integers(ints) => ints.map(|x| x as f64 / 255.0),
+        ColorResult::Floats(floats) => floats,
+        ColorResult::Exprs(_) => abort!(span, "Expressions are not supported, expected a constant color."),
+    }
+}
+
+/// A color stop, with a position in `0.0..=1.0` if given like `Red 25%`.
+pub(crate) struct Stop {
+    pub color: TokenStream,
+    pub position: Option<f64>,
+}
+
+/// Separate a trailing percentage from the color of a stop,
+/// a percentage after `/` like `Sky400 / 25%` is the alpha of the color.
+pub(crate) fn stop(tokens: TokenStream) -> Stop {
+    let tts: Vec<TokenTree> = tokens.clone().into_iter().collect();
+    if let [color @ .., TokenTree::Literal(lit), TokenTree::Punct(p)] = tts.as_slice() {
+        let alpha = matches!(color.last(), Some(TokenTree::Punct(p)) if p.as_char() == '/');
+        if p.as_char() == '%' && !color.is_empty() && !alpha {
+            let position = match lit.to_string().parse::<f64>() {
+                Ok(x) => x / 100.0,
+                Err(_) => abort!(lit.span(), "Expected a percentage like 25%."),
+            };
+            return Stop { color: color.iter().cloned().collect(), position: Some(position) }
+        }
+    }
+    Stop { color: tokens, position: None }
+}
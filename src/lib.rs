@@ -4,7 +4,7 @@
 //!
 //! ```
 //! # /*
-//! rgb!($path: path, $color_syntax: tt [=> {$($fields: ident)*}])
+//! rgb!($path: path, $color_syntax: tt [=> {$($fields: ident)*}] [, $($options),*])
 //! # */
 //! ```
 //!
@@ -93,15 +93,22 @@
 //!
 //! # Color Syntax
 //!
-//! The color is always a TokenTree `tt`.
+//! The color is always a TokenTree `tt`, except for bracketed color spaces like `hsl[210, 0.4, 0.5]`,
+//! color functions like `mix(Red, Blue)` and opacity like `Red / 50`.
 //!
 //! * Bracketed numbers: `[0.3, 0.72, 0.98]`, `[124, 54, 87, 255]`
 //! * Parenthesised expressions: `(0.3, 0.72, 0.98)`, `(r, g, b + g, a + 0.5)`
 //! * Splat syntax: `[0.3; 3]`, `[0.3; 3, 0.8]`, `[0.7; 4]`
 //! * Hex strings: `"AABBCC"`, `"AABBCCFF"`, `"#AABBCC"`, `"#AABBCCFF"`
 //! * Hex number literals: `0xAABBCC`, `0xAABBCCFF`
+//! * CSS functions: `"rgb(12 200 40 / 50%)"`, `"rgba(12, 200, 40, 0.5)"`
+//! * CSS color mixing: `"color-mix(in oklch, red 40%, blue)"`
+//! * CSS relative colors: `"rgb(from #336699 r g calc(b * 0.5))"`, `"oklch(from red calc(l + 0.1) c h)"`
+//! * Color spaces: `"hsl(210deg 40% 50%)"`, `"hwb(210 20% 30%)"`, `"oklch(0.7 0.15 250)"`, `"lab(52.2% 40.1 59.9)"`, `hsl[210, 0.4, 0.5]`, `oklab[0.7, -0.03, -0.09]`
 //! * CSS color names: `Red`, `Blue`
 //! * TailwindCSS color names: `Red100`, `Sky400`
+//! * Color functions: `mix(Red, Blue, 30%)`, `lighten(Sky400, 10%)`, `with_alpha("#AABBCC", 0.4)`
+//! * Opacity in percent: `Red / 50`, `Sky400 / 25%`
 //!
 //! # Details
 //!
@@ -146,10 +153,522 @@
 //! * `[v; 3, a]` means `[v, v, v, a]`
 //! * `[v; 4]` means `[v, v, v, v]`
 //! 
+//! ### CSS Functions
+//! Strings in CSS functional notation are parsed according to CSS Color 4,
+//! both the legacy comma syntax and the modern space syntax are supported.
+//!
+//! * Channels are numbers in `0..=255` or percentages.
+//! * Alpha is a number in `0.0..=1.0` or a percentage.
+//! * `none` is treated as `0`.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8,u8,u8,u8);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "rgb(12 200 40 / 50%)"), Color(12, 200, 40, 127));
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "rgba(100%, 0%, 50%, 1)"), Color(255, 0, 127, 255));
+//! ```
+//!
+//! `color-mix()` and relative colors from CSS Color 5 are evaluated at compile time.
+//! `color-mix()` supports the `srgb`, `srgb-linear`, `oklab` and `oklch` spaces and hue interpolation methods like `longer hue`.
+//! Relative colors replace channel keywords like `r`, `h` or `alpha` with the channels of the origin color,
+//! which can be a name, a hex color or another function. `calc()` supports `+`, `-`, `*` and `/`.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8,u8,u8,u8);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "color-mix(in srgb, red, blue)"), Color(127, 0, 127, 255));
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "rgb(from #336699 r g calc(b * 0.5))"), Color(0x33, 0x66, 0x4C, 255));
+//! assert_eq!(rgba!(Color, "hsl(from red calc(h + 120) s l)"), Color(0, 255, 0, 255));
+//! ```
+//!
+//! ### Color Spaces
+//! Colors in other color spaces are converted to sRGB at compile time.
+//! They can be written as CSS functions or in the bracketed syntax `space[channels]`.
+//!
+//! * `hsl` and `hsla`: hue, saturation, lightness.
+//! * `hwb`: hue, whiteness, blackness.
+//! * `oklab`: lightness in `0.0..=1.0`, a and b where `100%` is `0.4`.
+//! * `oklch`: lightness in `0.0..=1.0`, chroma where `100%` is `0.4`, hue.
+//! * `lab`: CIE Lab, lightness in `0..=100`, a and b where `100%` is `125`.
+//! * `lch`: CIE LCH, lightness in `0..=100`, chroma where `100%` is `150`, hue.
+//!
+//! Hues are numbers in degrees, or angles with units `deg`, `rad`, `grad` or `turn`.
+//! For `hsl` and `hwb`, CSS functions use percentages or numbers in `0..=100`,
+//! while the bracketed syntax uses percentages or numbers in `0.0..=1.0`.
+//! Alpha is optional in both.
+//!
+//! ```
+//! # use colorthis::rgbaf;
+//! # #[derive(Debug, PartialEq)] struct ColorF(f32,f32,f32,f32);
+//! assert_eq!(rgbaf!(ColorF, "hsl(210deg 40% 50%)"), ColorF(0.3, 0.5, 0.7, 1.0));
+//! assert_eq!(rgbaf!(ColorF, hsl[210, 0.4, 0.5, 0.5]), ColorF(0.3, 0.5, 0.7, 0.5));
+//! assert_eq!(rgbaf!(ColorF, hwb[0.5turn, 20%, 20%]), ColorF(0.2, 0.8, 0.8, 1.0));
+//! ```
+//!
+//! OKLab and OKLCH colors outside of the sRGB gamut are gamut mapped
+//! with the CSS Color 4 algorithm, which reduces chroma until the color fits.
+//! Enable feature `strict-gamut` to make them compile errors instead.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8,u8,u8,u8);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "oklch(0.7 0.1 250)"), Color(108, 163, 218, 255));
+//! # #[cfg(not(feature="strict-gamut"))]
+//! assert_eq!(rgba!(Color, oklch[0.7, 0.4, 30]), Color(255, 88, 67, 255));
+//! ```
+//!
+//! CIE Lab and LCH use the D50 white point and are adapted to sRGB's D65.
+//! They are not gamut mapped, so colors outside of sRGB are out of bounds like any other color,
+//! unless `unchecked` or `clamp` is enabled.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8,u8,u8,u8);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, "lab(52.2% 40.1 59.9)"), Color(197, 92, 6, 255));
+//! ```
+//!
+//! ```compile_fail
+//! # use colorthis::rgbaf;
+//! # fn color(r: f32, g: f32, b: f32, a: f32) {}
+//! rgbaf!(color, lab[50, 100, 100]);
+//! # #[cfg(feature="unchecked")] compile_error!();
+//! ```
+//!
 //! ### Color Names
 //! We relies on a [crate](https://docs.rs/parse-color/latest/parse_color/) 
 //! to parse and generate these data at compile time. No external support required.
 //! 
+//! # Color Functions
+//!
+//! Colors can be derived at compile time, arguments are colors or functions.
+//!
+//! * `mix(a, b [, t])` interpolates from `a` to `b` in OKLab, `t` defaults to `50%`
+//! * `lighten(color, amount)` and `darken(color, amount)` add to or subtract from the OKLCH lightness
+//! * `saturate(color, amount)` and `desaturate(color, amount)` scale the OKLCH chroma by `1 ± amount`
+//! * `with_alpha(color, alpha)` replaces the alpha
+//! * `color / opacity` replaces the alpha with an opacity in percent, like TailwindCSS
+//!
+//! Amounts are percentages like `10%` or fractions like `0.1`.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color, Red / 50), Color(255, 0, 0, 127));
+//! assert_eq!(rgba!(Color, mix(Red, Blue, 0%)), Color(255, 0, 0, 255));
+//! assert_eq!(rgba!(Color, darken(White, 100%)), Color(0, 0, 0, 255));
+//! let hover = rgba!(Color, lighten(Sky400, 10%));
+//! ```
+//!
+//! # Channel Order
+//!
+//! Channels can be reordered with a trailing `order` option,
+//! using the letters of the output like `rgba` or `hsla`.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Bgra(u8, u8, u8, u8);
+//! assert_eq!(rgba!(Bgra, "#11223344", order = bgra), Bgra(0x33, 0x22, 0x11, 0x44));
+//! ```
+//!
+//! This also applies to fields, the first field receives the first channel in `order`.
+//! `order` is not supported by `laba!`, since `a` is ambiguous.
+//!
+//! # Channel Types
+//!
+//! The `ty` option chooses the channel type, values are scaled to the range of the type
+//! and emitted with a suffix.
+//!
+//! | `ty` | range |
+//! | --- | --- |
+//! | `u8` | `0..=255` |
+//! | `u16` | `0..=65535` |
+//! | `i8` | `0..=127` |
+//! | `i16` | `0..=32767` |
+//! | `f32`, `f64` | `0.0..=1.0` |
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color16(u16, u16, u16, u16);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(rgba!(Color16, "#FF8000", ty = u16), Color16(65535, 32896, 0, 65535));
+//! ```
+//!
+//! Color spaces like `hsla!` only support `f32` and `f64`, values are not scaled.
+//! Parenthesised expressions cannot be converted to a channel type.
+//!
+//! # Rounding
+//!
+//! When floats are converted to integers, values are truncated by default, i.e. `0.5` becomes `127`.
+//! The `round` option chooses `half_even`, `half_up`, `floor` or `trunc`,
+//! and the `scale` option chooses between `x * 255` and `x * 256` clamped to `255`.
+//! This applies to all integer outputs including `ty` and packed colors.
+//!
+//! ```
+//! # use colorthis::rgba;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! assert_eq!(rgba!(Color, [0.5, 0.5, 0.5], round = half_up), Color(128, 128, 128, 255));
+//! assert_eq!(rgba!(Color, [0.5, 0.5, 0.5], scale = 256, round = trunc), Color(128, 128, 128, 255));
+//! ```
+//!
+//! # Output Color Spaces
+//!
+//! Besides RGB, colors can be converted to other color spaces at compile time,
+//! these macros always produce 4 floating point numbers.
+//!
+//! * `hsla!`: hue in degrees, saturation, lightness and alpha.
+//! * `hsva!`: hue in degrees, saturation, value and alpha.
+//! * `oklcha!`: OKLCH lightness, chroma, hue in degrees and alpha.
+//! * `laba!`: CIE Lab lightness in `0..=100`, a, b and alpha.
+//! * `lcha!`: CIE LCH lightness in `0..=100`, chroma, hue in degrees and alpha.
+//!
+//! ```
+//! # use colorthis::hsla;
+//! # #[derive(Debug, PartialEq)]
+//! enum Color { Hsla { hue: f32, saturation: f32, lightness: f32, alpha: f32 } }
+//! assert_eq!(
+//!     hsla!(Color::Hsla, Red => {hue, saturation, lightness, alpha}),
+//!     Color::Hsla { hue: 0.0, saturation: 1.0, lightness: 0.5, alpha: 1.0 }
+//! );
+//! ```
+//!
+//! Linear RGB, used by GPU-facing types, is produced by `rgb_linear!` and `rgba_linear!`,
+//! which apply the sRGB transfer function at compile time and leave alpha untouched.
+//!
+//! ```
+//! # use colorthis::rgba_linear;
+//! # #[derive(Debug, PartialEq)] struct LinearRgba(f32, f32, f32, f32);
+//! assert_eq!(rgba_linear!(LinearRgba, "#FFFFFF80"), LinearRgba(1.0, 1.0, 1.0, 128.0 / 255.0));
+//! ```
+//!
+//! Since the conversion happens at compile time, parenthesised expressions are not supported.
+//! ```compile_fail
+//! # use colorthis::hsla;
+//! # fn color(h: f32, s: f32, l: f32, a: f32) {}
+//! # let (r, g, b) = (1.0, 1.0, 1.0);
+//! hsla!(color, (r, g, b));
+//! ```
+//!
+//! # Premultiplied Alpha
+//!
+//! `rgba_premul!` and `rgbaf_premul!` multiply RGB by alpha at compile time,
+//! integers are rounded to the nearest value unless the `round` option or `round` in `colorthis.toml` is set,
+//! the `round-*` features don't apply. `scale` applies like to any other conversion.
+//!
+//! ```
+//! # use colorthis::{rgba_premul, rgbaf_premul};
+//! # #[derive(Debug, PartialEq)] struct Color(u8,u8,u8,u8);
+//! # #[derive(Debug, PartialEq)] struct ColorF(f32,f32,f32,f32);
+//! # #[cfg(not(feature="scale-256"))]
+//! assert_eq!(rgba_premul!(Color, [200, 100, 50, 128]), Color(100, 50, 25, 128));
+//! assert_eq!(rgbaf_premul!(ColorF, [1.0, 0.5, 0.25, 0.5]), ColorF(0.5, 0.25, 0.125, 0.5));
+//! ```
+//!
+//! For parenthesised expressions the multiplication is emitted as an expression,
+//! note the alpha expression is evaluated once for each channel.
+//! Without an alpha expression, the alpha in `colorthis.toml` is used.
+//!
+//! ```
+//! # use colorthis::rgbaf_premul;
+//! # #[derive(Debug, PartialEq)] struct ColorF(f32,f32,f32,f32);
+//! let alpha = 0.5;
+//! assert_eq!(rgbaf_premul!(ColorF, (1.0, 0.5, 0.25, alpha)), ColorF(0.5, 0.25, 0.125, 0.5));
+//! ```
+//!
+//! # Packed Colors
+//!
+//! `rgba_u32!`, `argb_u32!`, `abgr_u32!` and `bgra_u32!` pack a color into a single `u32`,
+//! `rgb565!` and `rgba4444!` pack a color into a `u16`. The path is optional.
+//!
+//! ```
+//! # use colorthis::{rgba_u32, argb_u32, rgb565};
+//! assert_eq!(rgba_u32!("#FF8000"), 0xFF8000FF);
+//! assert_eq!(argb_u32!(u32::swap_bytes, Red), 0x0000FFFF);
+//! assert_eq!(rgb565!(White), 0xFFFF);
+//! ```
+//!
+//! `order` reorders the channels of a layout and keeps the bit widths in place, `rgb565!` with `order = bgr`
+//! packs BGR565. `packed!` takes any layout with the `layout` option, channels followed by their bit width
+//! from the most significant bits, and packs into the smallest of `u8`, `u16`, `u32` and `u64` that fits.
+//!
+//! ```
+//! # use colorthis::{packed, rgb565, rgba4444};
+//! assert_eq!(rgb565!(Red, order = bgr), 0x001F);
+//! assert_eq!(rgba4444!(Red, order = bgra), packed!(Red, layout = b4g4r4a4));
+//! assert_eq!(packed!(Red, layout = a2r10g10b10, scale = 255), 0xFFF00000u32);
+//! assert_eq!(packed!(White, layout = r3g3b2), 0xFFu8);
+//! ```
+//!
+//! When packing into less than 8 bits, values are rescaled like `x * 31 / 255` and rounded with the `round`
+//! and `scale` options. Parenthesised expressions should be integers in `0..=255`, and are rounded the same way at runtime.
+//!
+//! # Palettes
+//!
+//! `palette!` converts many colors that share a path and fields, colors are separated by `,` and options
+//! follow a second `;`. `output` chooses the macro used for each color and defaults to `rgba`.
+//!
+//! ```
+//! # use colorthis::palette;
+//! # #[derive(Debug, PartialEq)] struct Color { r: u8, g: u8, b: u8, a: u8 }
+//! # #[derive(Debug, PartialEq)] struct Color3(u8, u8, u8);
+//! # impl Color3 { fn new(r: u8, g: u8, b: u8) -> Self { Color3(r, g, b) } }
+//! const PALETTE: [Color; 4] = palette!(Color => {r, g, b, a}; Red, "#00FF00", [0.2; 3], Sky400);
+//! let bgr = palette!(Color3::new; Red, Blue; output = rgb, order = bgr);
+//! ```
+//!
+//! With `enum = Name: Type`, an enum is generated instead, with `color(self) -> Type`, `name(self)`,
+//! `from_name(&str)` and `ALL` and `NAMES` constants. Colors are named like `Name = color`,
+//! a color that is a single identifier like `Red` is named by it.
+//!
+//! ```
+//! # use colorthis::palette;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! # impl Color { fn new(r: u8, g: u8, b: u8, a: u8) -> Self { Color(r, g, b, a) } }
+//! palette!(Color::new; Primary = "#1A73E8", Red; enum = Brand: Color);
+//!
+//! assert_eq!(Brand::Primary.color(), Color::new(0x1A, 0x73, 0xE8, 255));
+//! assert_eq!(Brand::from_name("Red"), Some(Brand::Red));
+//! assert_eq!(Brand::ALL.map(Brand::color), [Brand::Primary.color(), Brand::Red.color()]);
+//! ```
+//!
+//! # Gradients
+//!
+//! `gradient!` interpolates color stops into an array of `steps` colors at compile time.
+//! Stops can have a position like `Red 25%`, missing positions are spread evenly like in CSS gradients.
+//! `space` is one of `srgb`, `linear`, `oklab` (default) or `oklch`, and `hue` is one of `shorter` (default),
+//! `longer`, `increasing` or `decreasing` for `oklch`. Alpha is premultiplied during interpolation.
+//!
+//! ```
+//! # use colorthis::gradient;
+//! # #[derive(Debug, PartialEq)] struct Color { r: u8, g: u8, b: u8, a: u8 }
+//! const LUT: [Color; 3] = gradient!(Color => {r, g, b, a}; Black, White; steps = 3, space = srgb);
+//! # #[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+//! assert_eq!(LUT[1], Color { r: 127, g: 127, b: 127, a: 255 });
+//!
+//! let heat = gradient!(Color => {r, g, b, a}; Blue, Yellow 75%, Red; steps = 16, space = oklch, hue = longer);
+//! ```
+//!
+//! # Palette Files
+//!
+//! `include_palette!` reads a palette file relative to `CARGO_MANIFEST_DIR` at compile time,
+//! the crate is recompiled when the file changes.
+//! Supported formats are GIMP `.gpl`, JASC `.pal`, Lospec `.hex`, Paint.NET `.txt`,
+//! Adobe Swatch Exchange `.ase` and Photoshop `.aco`. RGB, HSB, CMYK, Lab and gray swatches in `.ase` and `.aco`
+//! are converted to sRGB, CMYK with the naive conversion of CSS `device-cmyk()`.
+//!
+//! ```
+//! # use colorthis::include_palette;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! # #[derive(Debug, PartialEq)] struct ColorF(f32, f32, f32, f32);
+//! // GIMP Palette
+//! // 255   0   0 Red
+//! //   0 128 255 Sky Blue
+//! //  17  34  51 Untitled
+//! const PALETTE: [Color; 3] = include_palette!(Color, "tests/palettes/test.gpl");
+//! let floats = include_palette!(ColorF, "tests/palettes/test.gpl", output = rgbaf);
+//! ```
+//!
+//! `output` chooses the macro used for each color and defaults to `rgba`, other options like
+//! `order` and `ty` are supported. With `consts = Type`, named constants are generated instead,
+//! unnamed colors are named `COLOR_{index}`.
+//!
+//! ```
+//! # use colorthis::include_palette;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! include_palette!(Color, "tests/palettes/test.gpl", consts = Color);
+//!
+//! assert_eq!(SKY_BLUE, Color(0, 128, 255, 255));
+//! assert_eq!(COLOR_2, Color(17, 34, 51, 255));
+//! ```
+//!
+//! # Design Tokens
+//!
+//! `include_tokens!` reads a [W3C design tokens](https://tr.designtokens.org/format/) JSON file
+//! relative to `CARGO_MANIFEST_DIR`, and generates a module for each group and a constant for each
+//! color token. `$type` is inherited from groups, aliases like `{color.base.red}` are resolved and
+//! tokens that are not colors are skipped. `$value` is a string with the color syntax,
+//! or a color object with `colorSpace` and `components`. Requires feature `tokens`.
+//!
+//! ```
+//! # #[cfg(feature="tokens")] mod doc {
+//! # use colorthis::include_tokens;
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! // { "color": { "$type": "color",
+//! //     "brand": { "primary": { "$value": "#1A73E8" }, "secondary": { "$value": "{color.base.red}" } },
+//! //     "base": { "red": { "$value": "rgb(255 0 0)" }, ... } } }
+//! include_tokens!(Color, "tests/tokens/tokens.json", consts = Color);
+//!
+//! # pub fn main() {
+//! assert_eq!(color::brand::PRIMARY, Color(0x1A, 0x73, 0xE8, 255));
+//! assert_eq!(color::brand::SECONDARY, Color(255, 0, 0, 255));
+//! # }
+//! # }
+//! # fn main() {
+//! # #[cfg(feature="tokens")] doc::main();
+//! # }
+//! ```
+//!
+//! `consts` is required, `output` and other options are the same as for `include_palette!`.
+//! Errors in the file name the key of the token.
+//!
+//! # Custom Colors
+//!
+//! `define_colors!` defines color names and a `macro_rules!` wrapper that passes them to other macros.
+//! Names that shadow built-in colors or colors in `colorthis.toml` must be marked `override`.
+//!
+//! ```
+//! # use colorthis::{define_colors, rgba};
+//! # #[derive(Debug, PartialEq)] struct Color(u8, u8, u8, u8);
+//! define_colors! {
+//!     macro brand;
+//!     BrandPrimary = "#1A73E8",
+//!     Danger600 = [220, 38, 38],
+//!     override Red = "#EE0000",
+//! }
+//!
+//! assert_eq!(brand!(rgba!(Color, BrandPrimary)), Color(0x1A, 0x73, 0xE8, 255));
+//! assert_eq!(brand!(rgba!(Color, Red)), Color(0xEE, 0, 0, 255));
+//! ```
+//!
+//! Custom colors are looked up before colors in `colorthis.toml`, then the built-in names.
+//! The wrapper works with every macro of this crate, including `palette!` and `gradient!`,
+//! by name like `rgba!` or by path like `colorthis::rgba!`.
+//! Use `pub(crate) use brand;` to use the wrapper in other modules.
+//!
+//! # Derive
+//!
+//! `#[derive(ColorThis)]` generates the `macro_rules!` wrapper of a color type,
+//! picking `rgba!` or `rgbaf!` from the channel types and the `=> {fields}` mapping from the field names.
+//!
+//! ```
+//! # use colorthis::ColorThis;
+//! #[derive(Debug, PartialEq, ColorThis)]
+//! struct Srgba {
+//!     red: f32,
+//!     green: f32,
+//!     blue: f32,
+//!     alpha: f32,
+//! }
+//!
+//! assert_eq!(srgba!(Red), Srgba { red: 1.0, green: 0.0, blue: 0.0, alpha: 1.0 });
+//! assert_eq!(srgba!(255, 0, 0), srgba!("#FF0000"));
+//! ```
+//!
+//! Channels are `r, g, b, a` or `red, green, blue, alpha`, alpha is optional,
+//! other fields are filled by `Default`. Tuple structs with 3 or 4 fields are in rgba order.
+//! Channels must be `u8`, `u16`, `i8`, `i16`, `f32` or `f64`.
+//!
+//! The macro is named after the type in snake case. Enums generate a macro for each variant marked `#[colorthis]`.
+//!
+//! ```
+//! # use colorthis::ColorThis;
+//! #[derive(ColorThis)]
+//! enum Color {
+//!     #[colorthis(macro = rgba)]
+//!     Rgba(u8, u8, u8, u8),
+//!     #[colorthis(linear)]
+//!     RgbaLinear { red: f32, green: f32, blue: f32, alpha: f32 },
+//!     Hsla { hue: f32, saturation: f32, lightness: f32, alpha: f32 },
+//! }
+//!
+//! let _ = rgba!(Sky400);
+//! let _ = rgba_linear!("#336699" / 50);
+//! ```
+//!
+//! `#[colorthis(...)]` accepts `macro = name`, `linear` for linear sRGB floats and `premul` for premultiplied alpha.
+//! The type must be in scope where the macro is used, and the macro is defined after the type like any `macro_rules!`.
+//!
+//! `#[derive(FromColorThis)]` reads the fields the same way and implements `colorthis_core::FromColorThis`,
+//! so `color!` constructs any color type, including generic ones. The generated `from_color_this`
+//! is a `const fn` unless other fields are filled by `Default`.
+//! Both expand to paths in `::colorthis_core`, so `colorthis-core` must be a direct dependency
+//! of the crate using them, with the same version as the one `colorthis` depends on.
+//!
+//! ```
+//! # use colorthis::{color, FromColorThis};
+//! use colorthis_core::{FromColorThis, Rgba};
+//!
+//! #[derive(Debug, PartialEq, FromColorThis)]
+//! struct Pixel(u8, u8, u8, u8);
+//!
+//! const ORANGE: Pixel = color!(Pixel, "#FF8800");
+//! assert_eq!(ORANGE, Pixel(255, 136, 0, 255));
+//!
+//! fn red<T: FromColorThis>() -> T {
+//!     color!(T, Red)
+//! }
+//! assert_eq!(red::<Rgba>(), Rgba::new(1.0, 0.0, 0.0, 1.0));
+//! ```
+//!
+//! Enums need exactly one variant marked `#[colorthis]`, `linear` and `premul` are supported,
+//! `macro = name` only applies to `derive(ColorThis)`.
+//!
+//! # Runtime Parsing
+//!
+//! The grammar lives in the [`colorthis-core`](https://docs.rs/colorthis-core) crate,
+//! which parses strings at runtime with the same grammar as the macros,
+//! for colors from config files or user input.
+//!
+//! ```
+//! use colorthis_core::{parse, Rgba};
+//!
+//! assert_eq!(parse("Sky400 / 50").unwrap().to_u8(), [56, 189, 248, 127]);
+//! assert_eq!(parse("\"#AABBCC\"").unwrap(), Rgba::new(0xAA as f32 / 255.0, 0xBB as f32 / 255.0, 0xCC as f32 / 255.0, 1.0));
+//! assert!(parse("[256, 0, 0]").is_err());
+//! ```
+//!
+//! Runtime parsing always uses the default configuration: expressions like `(r, g, b)`,
+//! custom colors and the names and `alpha` of `colorthis.toml` are only supported by the macros.
+//! `Rgba::to_u8` truncates like `round = trunc, scale = 255`, whatever the features enabled on `colorthis`.
+//!
+//! With the `serde` feature of `colorthis-core`, `colorthis_core::serde::Color` and the
+//! `deserialize_with` helpers in `colorthis_core::serde` read colors in any of these syntaxes
+//! from TOML, RON or JSON, including arrays like `[255, 136, 0]`.
+//!
+//! `colorthis_core::hex` parses hex colors in a `const fn` with the same rules as the macros,
+//! for crates that avoid proc macros. Invalid colors are compile errors in a `const`.
+//!
+//! ```
+//! const BRAND: [u8; 4] = colorthis_core::hex("#1A73E8");
+//! assert_eq!(BRAND, [0x1A, 0x73, 0xE8, 255]);
+//! ```
+//!
+//! # Configuration
+//!
+//! Defaults can be set per crate in a `colorthis.toml` next to `Cargo.toml`,
+//! or in a `[package.metadata.colorthis]` table in `Cargo.toml`.
+//!
+//! ```toml
+//! # "checked", "unchecked" or "clamp", overrides the `unchecked` and `clamp` features.
+//! check = "clamp"
+//! # default of the `round` option.
+//! round = "half_up"
+//! # default of the `scale` option.
+//! scale = 255
+//! # "map" or "strict", overrides the `strict-gamut` feature.
+//! gamut = "map"
+//! # alpha of colors without an alpha channel including color names, an integer in `0..=255` or a float in `0.0..=1.0`.
+//! alpha = 255
+//!
+//! # custom color names.
+//! [colors]
+//! Brand = "#FF8800"
+//! Accent = "oklch(0.7 0.1 200)"
+//!
+//! # custom colors that shadow built-in names.
+//! [overrides]
+//! Red = "#EE0000"
+//! ```
+//!
+//! Unlike features, these do not affect other crates in the dependency graph.
+//! Once a config is found, the crate is recompiled when it changes.
+//!
 //! # Feature Flags
 //!
 //! ## `unchecked` and `clamp`
@@ -164,6 +683,7 @@
 //! ```
 //!
 //! With `unchecked`, this *might* compile, assuming color accepts `1000` as an input.
+//! Out of range channels are passed through, unless a `ty` or packed output fixes the range, then they are clamped.
 //! ```
 //! # /*
 //! rgba!(color, [1000, 255, 128, 0]);
@@ -177,6 +697,20 @@
 //! # */
 //! ```
 //!
+//! ## `strict-gamut`
+//!
+//! Make colors outside of the sRGB gamut compile errors instead of gamut mapping them.
+//! ```compile_fail
+//! # use colorthis::rgba;
+//! # fn color(r: u8, g: u8, b: u8, a: u8) {}
+//! # #[cfg(not(feature="strict-gamut"))] compile_error!();
+//! rgba!(color, "oklch(0.7 0.4 30)");
+//! ```
+//!
+//! ## `round-half-even`, `round-half-up`, `round-floor` and `scale-256`
+//!
+//! Change the default `round` and `scale` options.
+//!
 //! ## `compact`
 //!
 //! Compact allows 3 or 4 letter compact colors to be compiled.
@@ -188,136 +722,388 @@
 //! # */
 //! ```
 //!
-use proc_macro_error::proc_macro_error;
+//! ## `tokens`
+//!
+//! Enables `include_tokens!`, off by default since it depends on `serde_json`.
+//!
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use proc_macro_error::{proc_macro_error, abort};
 use quote::quote;
 mod convert;
-use convert::Convert;
+use convert::Output;
 mod path;
-use path::extract_path;
+use path::{extract_path, extract_optional_path, extract_palette};
 mod parse;
-use parse::{ColorResult, parse_color, extract_idents};
+mod packed;
+mod options;
+mod config;
+mod colors;
+mod palette;
+#[cfg(feature="tokens")]
+mod tokens;
+mod gradient;
+mod derive;
+use config::config;
+use options::parse_options;
+use packed::Packing;
+use parse::{parse_color, extract_idents, ColorResult};
 
+/// Construct `path(items)`, or `path {fields: items}` if fields are specified,
+/// populating the rest of the struct with `Default::default()` if more fields are specified.
+fn construct(path: TokenStream, items: Vec<TokenStream>, idents: Option<Vec<Ident>>) -> TokenStream {
+    match idents {
+        None => quote!(#path (#(#items),*)),
+        Some(idents) if idents.len() > items.len() => {
+            let idents = &idents[..items.len()];
+            quote!(#path {#(#idents: #items),*, ..Default::default()})
+        },
+        Some(idents) => quote!(#path {#(#idents: #items),*}),
+    }
+}
 
-/// Converts color-like tokens into a function call or a struct constructor that receives 3 integers
-///
-/// Syntax:
-/// ```
-/// # /*
-/// rgb!(path, color_syntax [=> {fields}])
-/// # */
-/// ```
-#[proc_macro]
-#[proc_macro_error]
-pub fn rgb(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let (path, color, args) = extract_path(tokens.into());
-    let mut rgba = parse_color(color);
+fn expand(tokens: proc_macro::TokenStream, output: Output) -> proc_macro::TokenStream {
+    let (path, color, args, options) = extract_path(tokens.into());
+    let options = parse_options(options);
+    options.reject_palette();
+    options.reject_layout();
+    let idents = args.map(extract_idents);
+    let mut items = output.convert(parse_color(color, &options.colors), options.format());
+    if let Some(order) = &options.order {
+        items = output.reorder(items, order);
+    }
+    track(construct(path, items, idents)).into()
+}
+
+fn expand_color(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (path, color, args, options) = extract_path(tokens.into());
+    let options = parse_options(options);
+    options.reject_palette();
+    options.reject_layout();
     if let Some(args) = args {
-        rgba = rgba.with_idents(extract_idents(args))
+        abort!(args, "Fields are not supported by color!, they are set by #[derive(FromColorThis)].")
     }
-    match rgba {
-        ColorResult::Integers(ints) => {
-            let items = ints.to_int3();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Floats(floats) => {
-            let items = floats.to_int3();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Exprs(streams) => {
-            let streams = streams.into_iter().take(3)
-                .map(|x| x.unwrap_or(quote!(255)));
-            quote!(#path (#(#streams),*))
+    if let Some(order) = &options.order {
+        abort!(order.span(), "Option order is not supported by color!.")
+    }
+    if options.ty.is_some() {
+        abort!(Span::call_site(), "Option ty is not supported by color!, the channel type is set by #[derive(FromColorThis)].")
+    }
+    let color = parse_color(color, &options.colors);
+    if let ColorResult::Exprs(_) = color {
+        abort!(Span::call_site(), "Expressions are not supported by color!, expected a constant color.")
+    }
+    track(derive::channels(path, color, options.format())).into()
+}
+
+/// Expand a packed color, `layout` is fixed by the macro or given by the `layout` option of `packed!`.
+fn expand_packed(tokens: proc_macro::TokenStream, layout: Option<&str>) -> proc_macro::TokenStream {
+    let (path, color, options) = extract_optional_path(tokens.into());
+    let options = parse_options(options);
+    options.reject_palette();
+    if options.ty.is_some() {
+        abort!(Span::call_site(), "Channel type is fixed for packed colors.")
+    }
+    let mut packing = match (layout, &options.layout) {
+        (Some(layout), None) => Packing::parse(&Ident::new(layout, Span::call_site())),
+        (None, Some(layout)) => Packing::parse(layout),
+        (Some(_), Some(layout)) => abort!(layout.span(), "Option layout is only supported by packed!."),
+        (None, None) => abort!(Span::call_site(), "packed! requires the layout option, like `layout = r5g6b5`."),
+    };
+    if let Some(order) = &options.order {
+        packing = packing.reorder(order);
+    }
+    let value = packing.pack(parse_color(color, &options.colors), options.format());
+    track(match path {
+        Some(path) => quote!(#path (#value)),
+        None => value,
+    }).into()
+}
+
+fn expand_palette(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (path, file, args, options) = extract_path(tokens.into());
+    let options = parse_options(options);
+    options.reject_enum();
+    options.reject_layout();
+    options.reject_gradient();
+    let output = file_output(&options);
+    let file = file_name(file, "palette.gpl");
+    let file_path = palette::resolve(&file);
+    let entries = palette::read_palette(&file_path, file.span());
+    let idents = args.map(extract_idents);
+    let colors = entries.iter().map(|entry| {
+        let mut items = output.convert(entry.color.to_color(file.span()), options.format());
+        if let Some(order) = &options.order {
+            items = output.reorder(items, order);
+        }
+        construct(path.clone(), items, idents.clone())
+    });
+    let file_path = match file_path.to_str() {
+        Some(path) => path,
+        None => abort!(file.span(), "Palette path is not valid UTF-8."),
+    };
+    let config = config().track();
+    let Some(ty) = &options.consts else {
+        return quote!({
+            const _: &[u8] = include_bytes!(#file_path);
+            #config
+            [#(#colors),*]
+        }).into()
+    };
+    let mut names: Vec<String> = Vec::new();
+    let consts = entries.iter().zip(colors).enumerate().map(|(i, (entry, color))| {
+        let name = entry.name.as_deref()
+            .and_then(palette::const_name)
+            .unwrap_or_else(|| format!("COLOR_{}", i));
+        if names.contains(&name) {
+            abort!(file.span(), "Duplicate constant name {} in palette.", name)
+        }
+        names.push(name.clone());
+        let name = Ident::new(&name, file.span());
+        quote!(pub const #name: #ty = #color;)
+    }).collect::<Vec<_>>();
+    quote!(
+        const _: &[u8] = include_bytes!(#file_path);
+        #config
+        #(#consts)*
+    ).into()
+}
+
+/// Output of `palette!`, `include_palette!` and `include_tokens!`, `rgba!` unless set by the `output` option.
+fn file_output(options: &options::Options) -> Output {
+    match &options.output {
+        Some(name) => match Output::from_name(&name.to_string()) {
+            Some(output) => output,
+            None => abort!(name.span(), "Unknown output {}, expected a macro name like rgbaf.", name),
         },
-        ColorResult::IntFields(ints, idents) => {
-            let items = ints.to_int3();
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
+        None => Output::Int4,
+    }
+}
+
+fn file_name(tokens: TokenStream, example: &str) -> proc_macro2::Literal {
+    let mut iter = tokens.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Literal(lit)), None) => lit,
+        _ => abort!(Span::call_site(), "Expected a file name like \"{}\".", example),
+    }
+}
+
+fn expand_palette_macro(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (path, fields, entries, options) = extract_palette(tokens.into());
+    let options = parse_options(options);
+    options.reject_consts();
+    options.reject_layout();
+    options.reject_gradient();
+    let output = file_output(&options);
+    let idents = fields.map(extract_idents);
+    let colors = entries.iter().map(|entry| {
+        let mut items = output.convert(parse_color(entry.color.clone(), &options.colors), options.format());
+        if let Some(order) = &options.order {
+            items = output.reorder(items, order);
+        }
+        construct(path.clone(), items, idents.clone())
+    }).collect::<Vec<_>>();
+    let Some((name, ty)) = &options.palette_enum else {
+        return track(quote!([#(#colors),*])).into()
+    };
+    let mut variants: Vec<Ident> = Vec::new();
+    for entry in &entries {
+        let Some(variant) = &entry.name else {
+            abort!(entry.color, "Colors of an enum need a name, like `Name = color`.")
+        };
+        if variants.contains(variant) {
+            abort!(variant.span(), "Duplicate color name {}.", variant)
+        }
+        variants.push(variant.clone());
+    }
+    let names = variants.iter().map(|x| x.to_string());
+    let names2 = names.clone();
+    let len = variants.len();
+    let config = config().track();
+    quote!(
+        #config
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum #name {
+            #(#variants),*
+        }
+
+        impl #name {
+            /// All colors, in the order of the palette.
+            pub const ALL: [#name; #len] = [#(#name::#variants),*];
+            /// Names of the colors, in the order of the palette.
+            pub const NAMES: [&'static str; #len] = [#(#names),*];
+
+            pub fn color(self) -> #ty {
+                match self {
+                    #(#name::#variants => #colors),*
+                }
             }
-        },
-        ColorResult::FloatFields(floats, idents) => {
-            let items = floats.to_int3();
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
+
+            pub fn name(self) -> &'static str {
+                Self::NAMES[self as usize]
             }
-        },
-        ColorResult::ExprFields(streams, idents) => {
-            let streams = streams.into_iter().take(3)
-                .map(|x| x.unwrap_or(quote!(255)));
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #streams),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #streams),*})
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    #(#names2 => Some(#name::#variants),)*
+                    _ => None,
+                }
             }
         }
-    }.into()
+    ).into()
 }
 
-/// Converts color-like tokens into a function call or a struct constructor that receives 4 integers
-///
-/// Syntax:
-/// ```
-/// # /*
-/// rgba!(path, color_syntax [=> {fields}])
-/// # */
-/// ```
-#[proc_macro]
-#[proc_macro_error]
-pub fn rgba(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let (path, color, args) = extract_path(tokens.into());
-    let mut rgba = parse_color(color);
-    if let Some(args) = args {
-        rgba = rgba.with_idents(extract_idents(args))
+fn expand_gradient(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (path, fields, entries, options) = extract_palette(tokens.into());
+    let options = parse_options(options);
+    options.reject_consts();
+    options.reject_enum();
+    options.reject_layout();
+    let output = file_output(&options);
+    let Some(steps) = options.steps else {
+        abort!(Span::call_site(), "gradient! requires the steps option, like `steps = 16`.")
+    };
+    let space = options.space.unwrap_or_default();
+    if options.hue.is_some() && space != gradient::Space::Oklch {
+        abort!(Span::call_site(), "Option hue is only supported with space = oklch.")
     }
-    match rgba {
-        ColorResult::Integers(ints) => {
-            let items = ints.to_int4();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Floats(floats) => {
-            let items = floats.to_int4();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Exprs(streams) => {
-            let streams = streams.into_iter()
-                .map(|x| x.unwrap_or(quote!(255)));
-            quote!(#path (#(#streams),*))
-        },
-        ColorResult::IntFields(ints, idents) => {
-            let items = ints.to_int4();
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
-            }
-        },
-        ColorResult::FloatFields(floats, idents) =>  {
-            let items = floats.to_int4();
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
+    if entries.len() < 2 {
+        abort!(Span::call_site(), "Expected at least 2 color stops.")
+    }
+    let stops = entries.into_iter().map(|entry| {
+        // Single identifiers like `Red` are named by themselves.
+        if entry.name.is_some_and(|name| name != entry.color.to_string()) {
+            abort!(entry.color, "Color stops cannot be named.")
+        }
+        gradient::stop(entry.color)
+    }).collect::<Vec<_>>();
+    let positions = gradient::positions(&stops.iter().map(|x| x.position).collect::<Vec<_>>());
+    let stops = stops.into_iter().zip(positions).map(|(stop, position)| {
+        let span = stop.color.clone().into_iter().next().map(|x| x.span()).unwrap_or_else(Span::call_site);
+        (position, gradient::floats(parse_color(stop.color, &options.colors), span))
+    }).collect::<Vec<_>>();
+    let idents = fields.map(extract_idents);
+    let colors = gradient::gradient(&stops, steps, space, options.hue.unwrap_or_default())
+        .into_iter()
+        .map(|color| {
+            let mut items = output.convert(ColorResult::Floats(color), options.format());
+            if let Some(order) = &options.order {
+                items = output.reorder(items, order);
             }
-        },
-        ColorResult::ExprFields(streams, idents) => {
-            let streams = streams.into_iter()
-                .map(|x| x.unwrap_or(quote!(255)));
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #streams),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #streams),*})
+            construct(path.clone(), items, idents.clone())
+        });
+    track(quote!([#(#colors),*])).into()
+}
+
+#[cfg(feature="tokens")]
+fn expand_tokens(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (path, file, args, options) = extract_path(tokens.into());
+    let options = parse_options(options);
+    options.reject_enum();
+    options.reject_layout();
+    options.reject_gradient();
+    let output = file_output(&options);
+    let file = file_name(file, "tokens.json");
+    let Some(ty) = &options.consts else {
+        abort!(Span::call_site(), "include_tokens! requires the consts option, like `consts = Color`.")
+    };
+    let file_path = palette::resolve(&file);
+    let idents = args.map(extract_idents);
+    let colors = tokens::read_tokens(&file_path, file.span()).into_iter().map(|token| {
+        let mut items = output.convert(token.color, options.format());
+        if let Some(order) = &options.order {
+            items = output.reorder(items, order);
+        }
+        (token.path, construct(path.clone(), items, idents.clone()))
+    }).collect::<Vec<_>>();
+    let file_path = match file_path.to_str() {
+        Some(path) => path,
+        None => abort!(file.span(), "Tokens path is not valid UTF-8."),
+    };
+    let config = config().track();
+    let modules = token_modules(&colors, 0, ty, file.span());
+    quote!(
+        const _: &[u8] = include_bytes!(#file_path);
+        #config
+        #modules
+    ).into()
+}
+
+/// Constants for tokens at `depth`, and a module for each group.
+#[cfg(feature="tokens")]
+fn token_modules(colors: &[(Vec<String>, TokenStream)], depth: usize, ty: &TokenStream, span: Span) -> TokenStream {
+    let mut result = TokenStream::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut modules: Vec<String> = Vec::new();
+    for (i, (key, color)) in colors.iter().enumerate() {
+        let name = &key[depth];
+        if key.len() == depth + 1 {
+            let Some(ident) = palette::const_name(name) else {
+                abort!(span, "Token {} has no valid constant name.", key.join("."))
+            };
+            if names.contains(&ident) {
+                abort!(span, "Duplicate constant name {} for token {}.", ident, key.join("."))
             }
+            names.push(ident.clone());
+            let ident = Ident::new(&ident, span);
+            result.extend(quote!(pub const #ident: #ty = #color;));
+            continue;
+        }
+        let first = colors.iter().position(|(k, _)| k.len() > depth + 1 && k[..=depth] == key[..=depth]);
+        if first != Some(i) {
+            continue;
+        }
+        let Some(module) = tokens::module_name(name) else {
+            abort!(span, "Group {} has no valid module name.", key[..=depth].join("."))
+        };
+        if modules.contains(&module) {
+            abort!(span, "Duplicate module name {} for group {}.", module, key[..=depth].join("."))
         }
-    }.into()
+        modules.push(module.clone());
+        let group = colors.iter()
+            .filter(|(k, _)| k.len() > depth + 1 && k[..=depth] == key[..=depth])
+            .cloned()
+            .collect::<Vec<_>>();
+        let inner = token_modules(&group, depth + 1, ty, span);
+        let module = Ident::new(&module, span);
+        result.extend(quote!(pub mod #module { use super::*; #inner }));
+    }
+    result
+}
+
+/// Wrap the expression in a block that depends on the crate config, if there is one.
+fn track(expr: TokenStream) -> TokenStream {
+    match config().track() {
+        Some(track) => quote!({#track #expr}),
+        None => expr,
+    }
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives 3 integers
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgb!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgb(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Int3)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives 4 integers
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgba!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgba(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Int4)
 }
 
 /// Converts color-like tokens into a function call or a struct constructor that receives 3 flaoting point numbers
@@ -325,60 +1111,13 @@ pub fn rgba(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// Syntax:
 /// ```
 /// # /*
-/// rgbf!(path, color_syntax [=> {fields}])
+/// rgbf!(path, color_syntax [=> {fields}] [, options])
 /// # */
 /// ```
 #[proc_macro]
 #[proc_macro_error]
 pub fn rgbf(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let (path, color, args) = extract_path(tokens.into());
-    let mut rgba = parse_color(color);
-    if let Some(args) = args {
-        rgba = rgba.with_idents(extract_idents(args))
-    }
-    match rgba {
-        ColorResult::Integers(ints) => {
-            let items = ints.to_float3();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Floats(floats) => {
-            let items = floats.to_float3();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Exprs(streams) => {
-            let streams = streams.into_iter().take(3)
-                .map(|x| x.unwrap_or(quote!(1.0)));            
-            quote!(#path (#(#streams),*))
-        },
-        ColorResult::IntFields(ints, idents) => {
-            let items = ints.to_float3();
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
-            }
-        },
-        ColorResult::FloatFields(floats, idents) => {
-            let items = floats.to_float3();
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
-            }
-        },
-        ColorResult::ExprFields(streams, idents) => {
-            let streams = streams.into_iter().take(3)
-                .map(|x| x.unwrap_or(quote!(1.0)));
-            if idents.len() > 3 {
-                let idents = &idents[..3];
-                quote!(#path {#(#idents: #streams),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #streams),*})
-            }
-        },
-    }.into()
+    expand(tokens, Output::Float3)
 }
 
 /// Converts color-like tokens into a function call or a struct constructor that receives 4 flaoting point numbers
@@ -386,58 +1125,392 @@ pub fn rgbf(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// Syntax:
 /// ```
 /// # /*
-/// rgbaf!(path, color_syntax [=> {fields}])
+/// rgbaf!(path, color_syntax [=> {fields}] [, options])
 /// # */
 /// ```
 #[proc_macro]
 #[proc_macro_error]
 pub fn rgbaf(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let (path, color, args) = extract_path(tokens.into());
-    let mut rgba = parse_color(color);
-    if let Some(args) = args {
-        rgba = rgba.with_idents(extract_idents(args))
-    }
-    match rgba {
-        ColorResult::Integers(ints) => {
-            let items = ints.to_float4();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Floats(floats) => {
-            let items = floats.to_float4();
-            quote!(#path (#(#items),*))
-        },
-        ColorResult::Exprs(streams) => {
-            let streams = streams.into_iter()
-                .map(|x| x.unwrap_or(quote!(1.0)));
-            quote!(#path (#(#streams),*))
-        },
-        ColorResult::IntFields(ints, idents) => {
-            let items = ints.to_float4();
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
-            }
-        },
-        ColorResult::FloatFields(floats, idents) =>  {
-            let items = floats.to_float4();
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #items),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #items),*})
-            }
-        },
-        ColorResult::ExprFields(streams, idents) => {
-            let streams = streams.into_iter()
-                .map(|x| x.unwrap_or(quote!(1.0)));
-            if idents.len() > 4 {
-                let idents = &idents[..4];
-                quote!(#path {#(#idents: #streams),*, ..Default::default()})
-            } else {
-                quote!(#path {#(#idents: #streams),*})
-            }
-        }
-    }.into()
+    expand(tokens, Output::Float4)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// hue in degrees, saturation, lightness and alpha as floating point numbers.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// hsla!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn hsla(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Hsla)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// hue in degrees, saturation, value and alpha as floating point numbers.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// hsva!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn hsva(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Hsva)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// OKLCH lightness, chroma, hue in degrees and alpha as floating point numbers.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// oklcha!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn oklcha(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Oklcha)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// CIE Lab lightness in `0..=100`, a, b and alpha as floating point numbers.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// laba!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn laba(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Laba)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// CIE LCH lightness in `0..=100`, chroma, hue in degrees and alpha as floating point numbers.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// lcha!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn lcha(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Lcha)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// 3 floating point numbers in linear RGB.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgb_linear!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgb_linear(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Linear3)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// 4 floating point numbers in linear RGB, alpha is not affected.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgba_linear!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgba_linear(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::Linear4)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// 4 integers, with RGB multiplied by alpha.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgba_premul!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgba_premul(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::PremulInt4)
+}
+
+/// Converts color-like tokens into a function call or a struct constructor that receives
+/// 4 floating point numbers, with RGB multiplied by alpha.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgbaf_premul!(path, color_syntax [=> {fields}] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgbaf_premul(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(tokens, Output::PremulFloat4)
+}
+
+/// Converts color-like tokens into a `u32` in `0xRRGGBBAA` order,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgba_u32!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgba_u32(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("r8g8b8a8"))
+}
+
+/// Converts color-like tokens into a `u32` in `0xAARRGGBB` order,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// argb_u32!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn argb_u32(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("a8r8g8b8"))
+}
+
+/// Converts color-like tokens into a `u32` in `0xAABBGGRR` order,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// abgr_u32!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn abgr_u32(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("a8b8g8r8"))
+}
+
+/// Converts color-like tokens into a `u32` in `0xBBGGRRAA` order,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// bgra_u32!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn bgra_u32(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("b8g8r8a8"))
+}
+
+/// Converts color-like tokens into a `u16` with 5 bits red, 6 bits green and 5 bits blue,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgb565!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgb565(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("r5g6b5"))
+}
+
+/// Converts color-like tokens into a `u16` with 4 bits per channel in `0xRGBA` order,
+/// optionally passed to a function call.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// rgba4444!([path,] color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn rgba4444(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, Some("r4g4b4a4"))
+}
+
+/// Converts color-like tokens into an integer with the bit layout of the `layout` option,
+/// like `r5g6b5` from the most significant bits, optionally passed to a function call.
+/// The integer is the smallest of `u8`, `u16`, `u32` and `u64` that fits the layout.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// packed!([path,] color_syntax, layout = r5g6b5 [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn packed(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_packed(tokens, None)
+}
+
+/// Defines custom color names, and a `macro_rules!` wrapper that passes them to other macros.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// define_colors! {
+///     macro name;
+///     [override] Name = color_syntax,
+///     ...
+/// }
+/// name!(rgba!(path, Name, ...))
+/// name!(palette!(path; Name, ...))
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn define_colors(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    colors::define_colors(tokens.into()).into()
+}
+
+/// Used by the wrappers of `define_colors!` to add the colors to the options of a macro call.
+#[doc(hidden)]
+#[proc_macro]
+#[proc_macro_error]
+pub fn __with_colors(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    colors::with_colors(tokens.into()).into()
+}
+
+/// Generates a `macro_rules!` wrapper that constructs a color struct or enum variant from any color syntax.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// #[derive(ColorThis)]
+/// #[colorthis([macro = name] [, linear] [, premul])]
+/// struct Name { r: u8, g: u8, b: u8 [, a: u8] }
+/// name!(color_syntax)
+/// # */
+/// ```
+#[proc_macro_derive(ColorThis, attributes(colorthis))]
+#[proc_macro_error]
+pub fn derive_color_this(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive::derive(tokens.into()).into()
+}
+
+/// Generates `from_color_this` and an implementation of `colorthis_core::FromColorThis` for `color!`.
+///
+/// `colorthis-core` must be a dependency of the crate using this derive.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// #[derive(FromColorThis)]
+/// #[colorthis([linear] [, premul])]
+/// struct Name { r: u8, g: u8, b: u8 [, a: u8] }
+/// color!(Name, color_syntax)
+/// # */
+/// ```
+#[proc_macro_derive(FromColorThis, attributes(colorthis))]
+#[proc_macro_error]
+pub fn derive_from_color_this(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive::derive_from(tokens.into()).into()
+}
+
+/// Constructs any type implementing `colorthis_core::FromColorThis`, usable in a `const`
+/// for types with `#[derive(FromColorThis)]`.
+///
+/// `colorthis-core` must be a dependency of the crate using this macro.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// color!(Type, color_syntax [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn color(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_color(tokens)
+}
+
+/// Converts many colors that share a path and fields into an array, or an enum with `enum = Name: Type`.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// palette!(path [=> {fields}]; [Name =] color_syntax, ... [; options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn palette(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_palette_macro(tokens)
+}
+
+/// Interpolates color stops into an array of `steps` colors.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// gradient!(path [=> {fields}]; color_syntax [NN%], ...; steps = N [, space = oklab] [, hue = shorter] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn gradient(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_gradient(tokens)
+}
+
+/// Reads a GIMP `.gpl`, JASC `.pal`, Lospec `.hex`, Paint.NET `.txt`, Adobe `.ase` or `.aco` palette file
+/// relative to `CARGO_MANIFEST_DIR`, into an array of colors or named constants.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// include_palette!(path, "palette.gpl" [=> {fields}] [, output = rgbaf] [, consts = Type] [, options])
+/// # */
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn include_palette(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_palette(tokens)
+}
+
+/// Reads a W3C design tokens JSON file relative to `CARGO_MANIFEST_DIR`,
+/// into nested modules of color constants.
+///
+/// Syntax:
+/// ```
+/// # /*
+/// include_tokens!(path, "tokens.json" [=> {fields}], consts = Type [, output = rgbaf] [, options])
+/// # */
+/// ```
+#[cfg(feature="tokens")]
+#[proc_macro]
+#[proc_macro_error]
+pub fn include_tokens(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_tokens(tokens)
 }
@@ -0,0 +1,260 @@
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree, Spacing};
+use proc_macro_error::abort;
+use crate::config::config;
+use crate::colors::{CustomColor, parse_colors};
+use crate::convert::{ChannelType, Format, Rounding, Scale};
+use crate::gradient::{HueMethod, Space};
+
+/// Trailing `key = value` arguments of a macro.
+#[derive(Debug, Default)]
+pub(crate) struct Options {
+    /// Channel order like `bgra`.
+    pub order: Option<Ident>,
+    /// Channel type like `u16` or `f32`.
+    pub ty: Option<ChannelType>,
+    /// Rounding like `half_even` or `trunc`.
+    pub round: Option<Rounding>,
+    /// Quantization scale, `255` or `256`.
+    pub scale: Option<Scale>,
+    /// Custom colors passed by a `define_colors!` macro.
+    pub colors: Vec<CustomColor>,
+    /// Macro used for each color of a palette, like `rgbaf`.
+    pub output: Option<Ident>,
+    /// Type of named constants generated for a palette.
+    pub consts: Option<TokenStream>,
+    /// Name and color type of the enum generated by `palette!`, like `Brand: Color`.
+    pub palette_enum: Option<(Ident, TokenStream)>,
+    /// Number of colors of a gradient.
+    pub steps: Option<usize>,
+    /// Interpolation color space, like `oklch`.
+    pub space: Option<Space>,
+    /// Hue interpolation method, like `longer`.
+    pub hue: Option<HueMethod>,
+    /// Bit layout of `packed!`, like `r5g6b5`.
+    pub layout: Option<Ident>,
+}
+
+impl Options {
+    /// Abort if options only supported by `palette!`, `include_palette!` and `include_tokens!` are used.
+    pub fn reject_palette(&self) {
+        if let Some(output) = &self.output {
+            abort!(output.span(), "Option output is only supported by palette!, include_palette! and include_tokens!.")
+        }
+        self.reject_consts();
+        self.reject_enum();
+        self.reject_gradient();
+    }
+
+    pub fn reject_gradient(&self) {
+        if self.steps.is_some() || self.space.is_some() || self.hue.is_some() {
+            abort!(Span::call_site(), "Options steps, space and hue are only supported by gradient!.")
+        }
+    }
+
+    pub fn reject_layout(&self) {
+        if let Some(layout) = &self.layout {
+            abort!(layout.span(), "Option layout is only supported by packed!.")
+        }
+    }
+
+    pub fn reject_consts(&self) {
+        if let Some(consts) = &self.consts {
+            abort!(consts, "Option consts is only supported by include_palette! and include_tokens!.")
+        }
+    }
+
+    pub fn reject_enum(&self) {
+        if let Some((name, _)) = &self.palette_enum {
+            abort!(name.span(), "Option enum is only supported by palette!.")
+        }
+    }
+
+    /// Format of literals, unspecified options use the crate config, then the defaults set by features.
+    pub fn format(&self) -> Format {
+        let config = config();
+        Format {
+            ty: self.ty,
+            round: self.round.or(config.round).unwrap_or_default(),
+            premul_round: self.round.or(config.round).unwrap_or(Rounding::HalfUp),
+            scale: self.scale.or(config.scale).unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse `key = value` pairs separated by commas, empty pairs like `,,` are ignored.
+pub(crate) fn parse_options(tokens: TokenStream) -> Options {
+    let mut options = Options::default();
+    let mut iter = tokens.into_iter();
+    while let Some(tt) = iter.next() {
+        let key = match tt {
+            TokenTree::Punct(p) if p.as_char() == ',' => continue,
+            TokenTree::Ident(key) => key,
+            tt => abort!(tt.span(), "Expected option like `order = bgra`."),
+        };
+        match iter.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '=' && p.spacing() == Spacing::Alone => (),
+            _ => abort!(key.span(), "Expected = after option {}.", key),
+        }
+        let mut value = Vec::new();
+        for tt in iter.by_ref() {
+            match tt {
+                TokenTree::Punct(p) if p.as_char() == ',' => break,
+                tt => value.push(tt),
+            }
+        }
+        let value: TokenStream = value.into_iter().collect();
+        match key.to_string().as_str() {
+            "order" => set(&mut options.order, &key, single_ident(&key, value)),
+            "ty" => set(&mut options.ty, &key, channel_type(single_ident(&key, value))),
+            "round" => set(&mut options.round, &key, rounding(single_ident(&key, value))),
+            "scale" => set(&mut options.scale, &key, scale(&key, value)),
+            "colors" => options.colors.extend(parse_colors(brace(&key, value))),
+            "output" => set(&mut options.output, &key, single_ident(&key, value)),
+            "consts" if value.is_empty() => abort!(key.span(), "Expected a type for option {}.", key),
+            "consts" => set(&mut options.consts, &key, value),
+            "enum" => set(&mut options.palette_enum, &key, palette_enum(&key, value)),
+            "steps" => set(&mut options.steps, &key, steps(&key, value)),
+            "space" => set(&mut options.space, &key, space(single_ident(&key, value))),
+            "hue" => set(&mut options.hue, &key, hue_method(single_ident(&key, value))),
+            "layout" => set(&mut options.layout, &key, single_ident(&key, value)),
+            _ => abort!(key.span(), "Unknown option {}, expected one of `order`, `ty`, `round`, `scale`, `colors`, `output`, `consts`, `enum`, `steps`, `space`, `hue` or `layout`.", key),
+        }
+    }
+    options
+}
+
+fn set<T>(option: &mut Option<T>, key: &Ident, value: T) {
+    if option.is_some() {
+        abort!(key.span(), "Duplicate option {}.", key)
+    }
+    *option = Some(value);
+}
+
+fn brace(key: &Ident, value: TokenStream) -> TokenStream {
+    let mut iter = value.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Group(g)), None) if g.delimiter() == Delimiter::Brace => g.stream(),
+        (Some(tt), _) => abort!(tt.span(), "Expected {{...}} for option {}.", key),
+        (None, _) => abort!(key.span(), "Expected value for option {}.", key),
+    }
+}
+
+fn single_ident(key: &Ident, value: TokenStream) -> Ident {
+    let mut iter = value.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(ident)), None) => ident,
+        (Some(tt), _) => abort!(tt.span(), "Expected a single identifier for option {}.", key),
+        (None, _) => abort!(key.span(), "Expected value for option {}.", key),
+    }
+}
+
+/// Parse `Name: Type`.
+fn palette_enum(key: &Ident, value: TokenStream) -> (Ident, TokenStream) {
+    let mut iter = value.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(name)), Some(TokenTree::Punct(p))) if p.as_char() == ':' => {
+            let ty: TokenStream = iter.collect();
+            if ty.is_empty() {
+                abort!(p.span(), "Expected a color type after {}:.", name)
+            }
+            (name, ty)
+        },
+        (Some(tt), _) => abort!(tt.span(), "Expected `Name: Type` for option {}.", key),
+        (None, _) => abort!(key.span(), "Expected value for option {}.", key),
+    }
+}
+
+fn channel_type(ident: Ident) -> ChannelType {
+    match ident.to_string().as_str() {
+        "u8" => ChannelType::U8,
+        "u16" => ChannelType::U16,
+        "i8" => ChannelType::I8,
+        "i16" => ChannelType::I16,
+        "f32" => ChannelType::F32,
+        "f64" => ChannelType::F64,
+        _ => abort!(ident.span(), "Unsupported channel type {}, expected one of u8, u16, i8, i16, f32 or f64.", ident),
+    }
+}
+
+fn rounding(ident: Ident) -> Rounding {
+    match ident.to_string().as_str() {
+        "half_even" => Rounding::HalfEven,
+        "half_up" => Rounding::HalfUp,
+        "floor" => Rounding::Floor,
+        "trunc" => Rounding::Trunc,
+        _ => abort!(ident.span(), "Unsupported rounding {}, expected one of half_even, half_up, floor or trunc.", ident),
+    }
+}
+
+fn scale(key: &Ident, value: TokenStream) -> Scale {
+    let mut iter = value.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Literal(lit)), None) => match lit.to_string().as_str() {
+            "255" => Scale::S255,
+            "256" => Scale::S256,
+            _ => abort!(lit.span(), "Expected 255 or 256 for option {}.", key),
+        },
+        (Some(tt), _) => abort!(tt.span(), "Expected 255 or 256 for option {}.", key),
+        (None, _) => abort!(key.span(), "Expected value for option {}.", key),
+    }
+}
+
+fn steps(key: &Ident, value: TokenStream) -> usize {
+    let mut iter = value.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(TokenTree::Literal(lit)), None) => match lit.to_string().parse::<usize>() {
+            Ok(steps) if steps >= 1 => steps,
+            _ => abort!(lit.span(), "Expected a positive integer for option {}.", key),
+        },
+        (Some(tt), _) => abort!(tt.span(), "Expected a positive integer for option {}.", key),
+        (None, _) => abort!(key.span(), "Expected value for option {}.", key),
+    }
+}
+
+fn space(ident: Ident) -> Space {
+    match ident.to_string().as_str() {
+        "srgb" => Space::Srgb,
+        "linear" => Space::Linear,
+        "oklab" => Space::Oklab,
+        "oklch" => Space::Oklch,
+        _ => abort!(ident.span(), "Unsupported interpolation space {}, expected one of srgb, linear, oklab or oklch.", ident),
+    }
+}
+
+fn hue_method(ident: Ident) -> HueMethod {
+    match ident.to_string().as_str() {
+        "shorter" => HueMethod::Shorter,
+        "longer" => HueMethod::Longer,
+        "increasing" => HueMethod::Increasing,
+        "decreasing" => HueMethod::Decreasing,
+        _ => abort!(ident.span(), "Unsupported hue method {}, expected one of shorter, longer, increasing or decreasing.", ident),
+    }
+}
+
+/// Whether a comma separated segment starts like an option, i.e. `key = `.
+pub(crate) fn is_option(segment: &[TokenTree]) -> bool {
+    matches!(segment, [TokenTree::Ident(_), TokenTree::Punct(p), ..]
+        if p.as_char() == '=' && p.spacing() == Spacing::Alone)
+}
+
+/// Resolve a channel order like `bgra` against channel names like `rgba`,
+/// returns indices into the channels.
+pub(crate) fn channel_order(order: &Ident, names: &str) -> Vec<usize> {
+    let span: Span = order.span();
+    let order = order.to_string();
+    if order.len() != names.len() {
+        abort!(span, "Expected {} channels in order, found {}.", names.len(), order.len())
+    }
+    let mut indices: Vec<usize> = Vec::new();
+    for c in order.chars() {
+        let index = match names.find(c) {
+            Some(index) => index,
+            None => abort!(span, "Unknown channel {:?} in order, expected channels of {}.", c, names),
+        };
+        if indices.contains(&index) {
+            abort!(span, "Duplicate channel {:?} in order.", c)
+        }
+        indices.push(index);
+    }
+    indices
+}
@@ -0,0 +1,129 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use proc_macro_error::abort;
+use quote::quote;
+use crate::parse::ColorResult;
+use crate::convert::Format;
+use crate::config::config;
+use crate::options::channel_order;
+
+/// Integer type of a packed color, the smallest that fits the layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PackedType {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+/// Bit layout of a packed color.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Packing {
+    pub ty: PackedType,
+    /// Channel index (`0..4` for r, g, b, a) and bit width,
+    /// from the most significant to the least significant bits.
+    pub channels: Vec<(usize, u32)>,
+}
+
+impl Packing {
+    /// Parse a layout like `r5g6b5` or `a2r10g10b10`, channels followed by their bit width
+    /// from the most significant to the least significant bits.
+    pub fn parse(layout: &Ident) -> Packing {
+        let span = layout.span();
+        let mut channels: Vec<(usize, u32)> = Vec::new();
+        let mut chars = layout.to_string().chars().collect::<Vec<_>>().into_iter().peekable();
+        while let Some(c) = chars.next() {
+            let index = match "rgba".find(c) {
+                Some(index) => index,
+                None => abort!(span, "Unknown channel {:?} in layout, expected r, g, b or a.", c),
+            };
+            if channels.iter().any(|&(i, _)| i == index) {
+                abort!(span, "Duplicate channel {:?} in layout.", c)
+            }
+            let mut digits = String::new();
+            while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                digits.push(d);
+            }
+            match digits.parse::<u32>() {
+                Ok(bits) if (1..=16).contains(&bits) => channels.push((index, bits)),
+                _ => abort!(span, "Expected 1 to 16 bits after channel {:?} in layout, like `r5g6b5`.", c),
+            }
+        }
+        let ty = match channels.iter().map(|(_, bits)| bits).sum::<u32>() {
+            1..=8 => PackedType::U8,
+            9..=16 => PackedType::U16,
+            17..=32 => PackedType::U32,
+            33..=64 => PackedType::U64,
+            bits => abort!(span, "Layout {} has {} bits, expected at most 64.", layout, bits),
+        };
+        Packing { ty, channels }
+    }
+
+    /// Reorder the channels like `order = bgr`, the bit widths stay in place.
+    pub fn reorder(self, order: &Ident) -> Packing {
+        let letters: String = self.channels.iter().map(|&(index, _)| b"rgba"[index] as char).collect();
+        let channels = channel_order(order, &letters).into_iter()
+            .zip(&self.channels)
+            .map(|(i, &(_, bits))| (self.channels[i].0, bits))
+            .collect();
+        Packing { ty: self.ty, channels }
+    }
+
+    /// Bit offsets of each channel, paired with the channel index and bit width.
+    fn shifts(&self) -> impl Iterator<Item = (usize, u32, u32)> + '_ {
+        let mut shift: u32 = self.channels.iter().map(|(_, bits)| bits).sum();
+        self.channels.iter().map(move |&(index, bits)| {
+            shift -= bits;
+            (index, bits, shift)
+        })
+    }
+
+    /// Quantize a channel to `bits`, 8 bit channels are the same as in `rgba!`.
+    /// Out of range values from `unchecked` are clamped so they don't overflow into other channels.
+    fn quantize(color: &ColorResult, index: usize, bits: u32, format: Format) -> u64 {
+        let max = (1i64 << bits) - 1;
+        (match color {
+            ColorResult::Integers(ints) => format.rescale(ints[index], max),
+            ColorResult::Floats(floats) => format.quantize(floats[index], max),
+            ColorResult::Exprs(_) => unreachable!(),
+        }).clamp(0, max) as u64
+    }
+
+    /// Pack a parsed color into a single suffixed integer,
+    /// or an expression if the color is made of expressions.
+    pub fn pack(&self, color: ColorResult, format: Format) -> TokenStream {
+        if let ColorResult::Exprs(exprs) = &color {
+            let wide = match self.ty {
+                PackedType::U64 => quote!(u64),
+                _ => quote!(u32),
+            };
+            let parts = self.shifts().map(|(index, bits, shift)| {
+                let x = exprs[index].clone().unwrap_or_else(|| {
+                    let alpha = Literal::i64_unsuffixed(config().alpha_int());
+                    quote!(#alpha)
+                });
+                if bits == 8 {
+                    quote!((((#x) as #wide) << #shift))
+                } else {
+                    let max = (1u32 << bits) - 1;
+                    let x = format.rescale_expr(x, &quote!(#max));
+                    quote!(((#x as #wide) << #shift))
+                }
+            });
+            return match self.ty {
+                PackedType::U8 => quote!(((#(#parts)|*) as u8)),
+                PackedType::U16 => quote!(((#(#parts)|*) as u16)),
+                PackedType::U32 | PackedType::U64 => quote!((#(#parts)|*)),
+            }
+        }
+        let value = self.shifts()
+            .map(|(index, bits, shift)| Self::quantize(&color, index, bits, format) << shift)
+            .fold(0, |acc, x| acc | x);
+        let lit = match self.ty {
+            PackedType::U8 => Literal::u8_suffixed(value as u8),
+            PackedType::U16 => Literal::u16_suffixed(value as u16),
+            PackedType::U32 => Literal::u32_suffixed(value as u32),
+            PackedType::U64 => Literal::u64_suffixed(value),
+        };
+        quote!(#lit)
+    }
+}
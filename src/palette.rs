@@ -0,0 +1,420 @@
+use std::path::PathBuf;
+use proc_macro2::{Literal, Span, TokenTree};
+use proc_macro_error::abort;
+use crate::parse::{parse_color, ColorResult};
+
+/// A color in a palette file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub name: Option<String>,
+    pub color: Swatch,
+}
+
+/// Color of a palette entry.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Swatch {
+    /// 8 bit RGBA.
+    Ints([i64; 4]),
+    /// RGBA in `0.0..=1.0`.
+    Floats([f64; 4]),
+    /// A color in another color space, converted with the color syntax like `lab(50 20 -30)`.
+    Css(String),
+}
+
+impl Swatch {
+    pub fn to_color(&self, span: Span) -> ColorResult {
+        let mut color = match self {
+            Swatch::Ints(ints) => ColorResult::Integers(*ints),
+            Swatch::Floats(floats) => ColorResult::Floats(*floats),
+            Swatch::Css(css) => {
+                let mut lit = Literal::string(css);
+                lit.set_span(span);
+                return parse_color(TokenTree::Literal(lit).into(), &[])
+            },
+        };
+        color.check(span);
+        color
+    }
+}
+
+/// Resolve a string literal relative to `CARGO_MANIFEST_DIR`.
+pub(crate) fn resolve(lit: &Literal) -> PathBuf {
+    let file = match litrs::StringLit::try_from(lit.clone()) {
+        Ok(s) => s.value().to_owned(),
+        Err(_) => abort!(lit.span(), "Expected a file name string."),
+    };
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir).join(file),
+        None => PathBuf::from(file),
+    }
+}
+
+/// Read a palette file, the format is chosen by the extension.
+pub(crate) fn read_palette(path: &PathBuf, span: Span) -> Vec<Entry> {
+    let ext = path.extension().and_then(|x| x.to_str()).unwrap_or("").to_ascii_lowercase();
+    let src = match std::fs::read(path) {
+        Ok(src) => src,
+        Err(e) => abort!(span, "Cannot read {}: {}.", path.display(), e),
+    };
+    let binary = match ext.as_str() {
+        "ase" => Some(parse_ase(&src)),
+        "aco" => Some(parse_aco(&src)),
+        _ => None,
+    };
+    if let Some(result) = binary {
+        return match result {
+            Ok(entries) if entries.is_empty() => abort!(span, "{}: palette is empty.", path.display()),
+            Ok(entries) => entries,
+            Err(e) => abort!(span, "{}: {}", path.display(), e),
+        }
+    }
+    let text = || match String::from_utf8(src.clone()) {
+        Ok(s) => s,
+        Err(_) => abort!(span, "{} is not valid UTF-8.", path.display()),
+    };
+    let result = match ext.as_str() {
+        "gpl" => parse_gpl(&text()),
+        "pal" => parse_jasc(&text()),
+        "hex" => parse_hex(&text()),
+        "txt" => parse_paint_net(&text()),
+        _ => abort!(span, "Unsupported palette format {}, expected .gpl, .pal, .hex, .txt, .ase or .aco.", path.display()),
+    };
+    match result {
+        Ok(entries) if entries.is_empty() => abort!(span, "{}: palette is empty.", path.display()),
+        Ok(entries) => entries,
+        Err((line, e)) => abort!(span, "{}:{}: {}", path.display(), line, e),
+    }
+}
+
+/// Error with a 1 based line number.
+type Error = (usize, String);
+
+/// Non empty lines with their line numbers.
+fn lines(src: &str) -> impl Iterator<Item = (usize, &str)> {
+    src.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+fn channel(s: &str, line: usize) -> Result<i64, Error> {
+    match s.parse::<i64>() {
+        Ok(i) if (0..=255).contains(&i) => Ok(i),
+        _ => Err((line, format!("Expected an integer in 0..=255, found {:?}.", s))),
+    }
+}
+
+fn hex_color(s: &str, line: usize) -> Result<[i64; 4], Error> {
+    let s = s.strip_prefix('#').unwrap_or(s);
+    let v = match (s.len(), u32::from_str_radix(s, 16)) {
+        (6 | 8, Ok(v)) => v as i64,
+        _ => return Err((line, format!("Expected a hex color like FF8800, found {:?}.", s))),
+    };
+    Ok(if s.len() == 6 {
+        [v >> 16 & 0xFF, v >> 8 & 0xFF, v & 0xFF, 255]
+    } else {
+        [v >> 24 & 0xFF, v >> 16 & 0xFF, v >> 8 & 0xFF, v & 0xFF]
+    })
+}
+
+/// GIMP palette, `R G B [name]` after a `GIMP Palette` header.
+/// GIMP 3 palettes with `Channels: RGBA` have an alpha column.
+fn parse_gpl(src: &str) -> Result<Vec<Entry>, Error> {
+    let mut lines = lines(src);
+    match lines.next() {
+        Some((_, "GIMP Palette")) => (),
+        _ => return Err((1, "Expected a `GIMP Palette` header.".to_owned())),
+    }
+    let mut channels = 3;
+    let mut entries = Vec::new();
+    for (line, s) in lines {
+        if s.starts_with('#') || s.starts_with("Name:") || s.starts_with("Columns:") {
+            continue;
+        }
+        if let Some(c) = s.strip_prefix("Channels:") {
+            channels = match c.trim() {
+                "RGB" => 3,
+                "RGBA" => 4,
+                c => return Err((line, format!("Unsupported channels {:?}.", c))),
+            };
+            continue;
+        }
+        let mut parts = s.split_whitespace();
+        let mut color = [0, 0, 0, 255];
+        for c in color.iter_mut().take(channels) {
+            match parts.next() {
+                Some(x) => *c = channel(x, line)?,
+                None => return Err((line, format!("Expected {} channels.", channels))),
+            }
+        }
+        let name = parts.collect::<Vec<_>>().join(" ");
+        let name = if name.is_empty() || name == "Untitled" { None } else { Some(name) };
+        entries.push(Entry { name, color: Swatch::Ints(color) });
+    }
+    Ok(entries)
+}
+
+/// JASC palette, `JASC-PAL`, `0100`, the number of colors, then `R G B` lines.
+fn parse_jasc(src: &str) -> Result<Vec<Entry>, Error> {
+    let mut lines = lines(src);
+    match (lines.next(), lines.next()) {
+        (Some((_, "JASC-PAL")), Some((_, "0100"))) => (),
+        _ => return Err((1, "Expected a `JASC-PAL` header, binary RIFF palettes are not supported.".to_owned())),
+    }
+    let count = match lines.next() {
+        Some((line, s)) => match s.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => return Err((line, format!("Expected the number of colors, found {:?}.", s))),
+        },
+        None => return Err((3, "Expected the number of colors.".to_owned())),
+    };
+    let mut entries = Vec::new();
+    for (line, s) in lines {
+        let parts: Vec<_> = s.split_whitespace().collect();
+        let color = match parts.as_slice() {
+            [r, g, b] => [channel(r, line)?, channel(g, line)?, channel(b, line)?, 255],
+            [r, g, b, a] => [channel(r, line)?, channel(g, line)?, channel(b, line)?, channel(a, line)?],
+            _ => return Err((line, format!("Expected `R G B`, found {:?}.", s))),
+        };
+        entries.push(Entry { name: None, color: Swatch::Ints(color) });
+    }
+    if entries.len() != count {
+        return Err((3, format!("Expected {} colors, found {}.", count, entries.len())))
+    }
+    Ok(entries)
+}
+
+/// Lospec hex palette, one `RRGGBB` per line.
+fn parse_hex(src: &str) -> Result<Vec<Entry>, Error> {
+    lines(src)
+        .map(|(line, s)| Ok(Entry { name: None, color: Swatch::Ints(hex_color(s, line)?) }))
+        .collect()
+}
+
+/// Paint.NET palette, one `AARRGGBB` per line, comments start with `;`.
+fn parse_paint_net(src: &str) -> Result<Vec<Entry>, Error> {
+    lines(src)
+        .filter(|(_, s)| !s.starts_with(';'))
+        .map(|(line, s)| {
+            if s.len() != 8 {
+                return Err((line, format!("Expected a hex color like FFFF8800, found {:?}.", s)))
+            }
+            let [a, r, g, b] = hex_color(s, line)?;
+            Ok(Entry { name: None, color: Swatch::Ints([r, g, b, a]) })
+        })
+        .collect()
+}
+
+/// Reads big endian values from a binary palette.
+struct Bytes<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        match self.src.get(self.pos..self.pos + n) {
+            Some(bytes) => {
+                self.pos += n;
+                Ok(bytes)
+            },
+            None => Err(format!("Unexpected end of file at byte {}.", self.pos)),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        self.u32().map(f32::from_bits)
+    }
+
+    /// A UTF-16 string of `len` code units, the null terminator is removed.
+    fn utf16(&mut self, len: usize) -> Result<String, String> {
+        let units = (0..len).map(|_| self.u16()).collect::<Result<Vec<_>, _>>()?;
+        let units = units.strip_suffix(&[0]).unwrap_or(&units);
+        String::from_utf16(units).map_err(|_| format!("Invalid UTF-16 name before byte {}.", self.pos))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+}
+
+/// Naive CMYK to sRGB conversion like CSS `device-cmyk()`, channels in `0.0..=1.0`.
+fn cmyk(c: f64, m: f64, y: f64, k: f64) -> Swatch {
+    let f = |x: f64| 1.0 - (x * (1.0 - k) + k).min(1.0);
+    Swatch::Floats([f(c), f(m), f(y), 1.0])
+}
+
+fn gray(x: f64) -> Swatch {
+    Swatch::Floats([x, x, x, 1.0])
+}
+
+/// Adobe Swatch Exchange, `ASEF` blocks with RGB, CMYK, Lab or gray float colors.
+fn parse_ase(src: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut bytes = Bytes { src, pos: 0 };
+    if bytes.take(4)? != b"ASEF" {
+        return Err("Expected an `ASEF` header.".to_owned())
+    }
+    bytes.take(4)?;
+    let count = bytes.u32()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let kind = bytes.u16()?;
+        let len = bytes.u32()? as usize;
+        if kind != 0x0001 {
+            // Group start and end.
+            bytes.take(len)?;
+            continue;
+        }
+        let end = bytes.pos + len;
+        let name_len = bytes.u16()? as usize;
+        let name = bytes.utf16(name_len)?;
+        let model = bytes.take(4)?;
+        let mut floats = |n: usize| (0..n).map(|_| bytes.f32().map(f64::from)).collect::<Result<Vec<_>, _>>();
+        let color = match model {
+            b"RGB " => match floats(3)?[..] {
+                [r, g, b] => Swatch::Floats([r, g, b, 1.0]),
+                _ => unreachable!(),
+            },
+            b"CMYK" => match floats(4)?[..] {
+                [c, m, y, k] => cmyk(c, m, y, k),
+                _ => unreachable!(),
+            },
+            b"LAB " => match floats(3)?[..] {
+                [l, a, b] => Swatch::Css(format!("lab({} {} {})", l * 100.0, a, b)),
+                _ => unreachable!(),
+            },
+            b"Gray" => gray(floats(1)?[0]),
+            model => return Err(format!("Unsupported color model {:?} for {}.", String::from_utf8_lossy(model), name)),
+        };
+        bytes.pos = end;
+        let name = if name.is_empty() { None } else { Some(name) };
+        entries.push(Entry { name, color });
+    }
+    Ok(entries)
+}
+
+/// Photoshop color swatches, version 1 colors optionally followed by version 2 colors with names.
+fn parse_aco(src: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut bytes = Bytes { src, pos: 0 };
+    let mut entries = Vec::new();
+    while !bytes.is_empty() {
+        let version = bytes.u16()?;
+        if version != 1 && version != 2 {
+            return Err(format!("Unsupported version {}.", version))
+        }
+        let count = bytes.u16()?;
+        entries.clear();
+        for _ in 0..count {
+            let space = bytes.u16()?;
+            let [w, x, y, z] = [bytes.u16()?, bytes.u16()?, bytes.u16()?, bytes.u16()?];
+            let name = if version == 2 {
+                let len = bytes.u32()? as usize;
+                Some(bytes.utf16(len)?).filter(|x| !x.is_empty())
+            } else {
+                None
+            };
+            let [w, x, y, z] = [w, x, y, z].map(f64::from);
+            let color = match space {
+                0 => Swatch::Floats([w / 65535.0, x / 65535.0, y / 65535.0, 1.0]),
+                1 => {
+                    let [hue, sat, value] = [w / 65535.0 * 360.0, x / 65535.0, y / 65535.0];
+                    Swatch::Css(format!("hwb({} {}% {}%)", hue, (1.0 - sat) * value * 100.0, (1.0 - value) * 100.0))
+                },
+                // 0 is full ink.
+                2 => cmyk(1.0 - w / 65535.0, 1.0 - x / 65535.0, 1.0 - y / 65535.0, 1.0 - z / 65535.0),
+                7 => {
+                    let [a, b] = [x, y].map(|c| c as u16 as i16 as f64 / 100.0);
+                    Swatch::Css(format!("lab({} {} {})", w / 100.0, a, b))
+                },
+                // Gray is the amount of black ink.
+                8 => gray(1.0 - w / 10000.0),
+                9 => cmyk(w / 10000.0, x / 10000.0, y / 10000.0, z / 10000.0),
+                space => return Err(format!("Unsupported color space {}.", space)),
+            };
+            entries.push(Entry { name, color });
+        }
+    }
+    Ok(entries)
+}
+
+/// Convert a color name like `Dark Red 2` to a constant name like `DARK_RED_2`.
+pub(crate) fn const_name(name: &str) -> Option<String> {
+    let mut result = String::new();
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|x| !x.is_empty()) {
+        if !result.is_empty() {
+            result.push('_');
+        }
+        result.push_str(&word.to_ascii_uppercase());
+    }
+    if result.is_empty() {
+        return None
+    }
+    if result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert_str(0, "COLOR_");
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_palette_formats() {
+        let gpl = "GIMP Palette\nName: Test\nColumns: 4\n# comment\n255   0   0\tRed\n  0 128 255 Sky Blue\n0 0 0 Untitled\n";
+        assert_eq!(parse_gpl(gpl).unwrap(), vec![
+            Entry { name: Some("Red".to_owned()), color: Swatch::Ints([255, 0, 0, 255]) },
+            Entry { name: Some("Sky Blue".to_owned()), color: Swatch::Ints([0, 128, 255, 255]) },
+            Entry { name: None, color: Swatch::Ints([0, 0, 0, 255]) },
+        ]);
+        let gpl = "GIMP Palette\nChannels: RGBA\n255 0 0 128 Red\n";
+        assert_eq!(parse_gpl(gpl).unwrap()[0].color, Swatch::Ints([255, 0, 0, 128]));
+        assert_eq!(parse_gpl("GIMP Palette\n256 0 0\n").unwrap_err().0, 2);
+
+        let pal = "JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 128 255\r\n";
+        assert_eq!(parse_jasc(pal).unwrap()[1].color, Swatch::Ints([0, 128, 255, 255]));
+        assert!(parse_jasc("JASC-PAL\n0100\n3\n255 0 0\n").is_err());
+
+        assert_eq!(parse_hex("ff0000\n#0080FF\n\n").unwrap()[1].color, Swatch::Ints([0, 128, 255, 255]));
+        assert!(parse_hex("ff00\n").is_err());
+
+        let txt = "; paint.net Palette File\nFFFF0000\n800080FF\n";
+        assert_eq!(parse_paint_net(txt).unwrap()[1].color, Swatch::Ints([0, 128, 255, 128]));
+        assert!(parse_paint_net("FF0000\n").is_err());
+    }
+
+    #[test]
+    pub fn test_binary_palettes() {
+        let ase = parse_ase(include_bytes!("../tests/palettes/test.ase")).unwrap();
+        assert_eq!(ase, vec![
+            Entry { name: Some("Red".to_owned()), color: Swatch::Floats([1.0, 0.0, 0.0, 1.0]) },
+            Entry { name: Some("Cyan".to_owned()), color: Swatch::Floats([0.0, 1.0, 1.0, 1.0]) },
+            Entry { name: Some("Lab White".to_owned()), color: Swatch::Css("lab(100 0 0)".to_owned()) },
+            Entry { name: Some("Gray".to_owned()), color: Swatch::Floats([0.5, 0.5, 0.5, 1.0]) },
+        ]);
+        assert!(parse_ase(b"ASEF\0\x01\0\0\0\0\0\x01\0\x01").is_err());
+
+        let aco = parse_aco(include_bytes!("../tests/palettes/test.aco")).unwrap();
+        assert_eq!(aco.len(), 5);
+        assert_eq!(aco[1], Entry { name: Some("Cyan".to_owned()), color: Swatch::Floats([0.0, 1.0, 1.0, 1.0]) });
+        assert_eq!(aco[2].color, Swatch::Css("lab(100 0 0)".to_owned()));
+        assert_eq!(aco[3].color, Swatch::Floats([0.75, 0.75, 0.75, 1.0]));
+        assert_eq!(aco[4].color, Swatch::Css("hwb(0 0% 0%)".to_owned()));
+        assert!(parse_aco(b"\0\x03\0\0").is_err());
+    }
+
+    #[test]
+    pub fn test_const_name() {
+        assert_eq!(const_name("Sky Blue").unwrap(), "SKY_BLUE");
+        assert_eq!(const_name("dark-red (2)").unwrap(), "DARK_RED_2");
+        assert_eq!(const_name("50% Gray").unwrap(), "COLOR_50_GRAY");
+        assert_eq!(const_name("???"), None);
+    }
+}
@@ -1,45 +1,12 @@
-use proc_macro2::{TokenStream, TokenTree, Span, Spacing, Delimiter};
+use proc_macro2::{Ident, TokenStream, TokenTree, Span, Spacing, Delimiter};
 use proc_macro_error::abort;
 use quote::quote;
-
-enum PathSep {
-    DoubleColon,
-    Dot,
-    Break,
-}
-
-impl PathSep {
-    pub fn write_to(&self, tokens: &mut TokenStream) {
-        match &self {
-            PathSep::DoubleColon => tokens.extend(quote!(::)),
-            PathSep::Dot => tokens.extend(quote!(.)),
-            PathSep::Break => (),
-        }
-    }
-}
-
-fn extract_sep(iter: &mut proc_macro2::token_stream::IntoIter) -> PathSep{
-    match iter.next() {
-        Some(TokenTree::Punct(punct)) if punct.as_char() == ':' && punct.spacing() == Spacing::Joint => {
-            match iter.next() {
-                Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => PathSep::DoubleColon,
-                Some(tt) => abort!(tt.span(), "Expected '::'."),
-                None => abort!(punct.span(), "Expected '::'."),
-            }
-        }
-        Some(TokenTree::Punct(punct)) if punct.as_char() == '.' => {
-            PathSep::Dot
-        },
-        Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {
-            PathSep::Break
-        },
-        Some(tt) => abort!(tt.span(), "Expected one of ',', '.' or '::'."),
-        None => abort!(Span::call_site(), "Expected one of ',', '.' or '::'."),
-    }
-}
+use crate::options::is_option;
 
 /// Separate caller paths like `Rgba::new`, `::colors::rgba::rgba`, `color.set`, etc.
-pub fn extract_path(tokens: TokenStream) -> (TokenStream, TokenTree, Option<TokenStream>) {
+///
+/// Returns the path, the color, optional fields and the trailing options.
+pub fn extract_path(tokens: TokenStream) -> (TokenStream, TokenStream, Option<TokenStream>, TokenStream) {
     let mut tokens = tokens.into_iter();
     let mut path = Vec::new();
     let path = loop {
@@ -55,25 +22,26 @@ pub fn extract_path(tokens: TokenStream) -> (TokenStream, TokenTree, Option<Toke
             }
         }
     };
-    let color = match tokens.next(){
-        Some(x) => x,
-        None => abort!(Span::call_site(), "Expected color token tree."),
-    };
-    match (tokens.next(), tokens.next()) {
-        (None, _) => return (path, color, None),
-        (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b))) => {
-            if a.as_char() != '=' || b.as_char() != '>' || a.spacing() != Spacing::Joint{
-                abort!(a.span(), "Expected => , or end.")
-            }
-        }
-        (Some(TokenTree::Punct(p)), None) => {
-            if p.as_char() == ',' {
-                return (path, color, None)
-            } else {
-                abort!(p.span(), "Expected => , or end.")
+    let mut color = Vec::new();
+    let has_fields = loop {
+        match tokens.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ',' => break false,
+            Some(TokenTree::Punct(p)) if p.as_char() == '=' && p.spacing() == Spacing::Joint => {
+                match tokens.next() {
+                    Some(TokenTree::Punct(b)) if b.as_char() == '>' => break true,
+                    _ => abort!(p.span(), "Expected => , or end."),
+                }
             }
+            Some(tt) => color.push(tt),
+            None => break false,
         }
-        (Some(tt), _) => abort!(tt.span(), "Expected => , or end.")
+    };
+    if color.is_empty() {
+        abort!(Span::call_site(), "Expected color token tree.")
+    }
+    let color = color.into_iter().collect();
+    if !has_fields {
+        return (path, color, None, tokens.collect())
     }
 
     let fields = match tokens.next() {
@@ -85,22 +53,117 @@ pub fn extract_path(tokens: TokenStream) -> (TokenStream, TokenTree, Option<Toke
     };
 
     match tokens.next() {
-        Some(TokenTree::Punct(p)) if p.as_char() == ',' => {
-            match tokens.next() {
-                Some(t) => abort!(t.span(), "Expected end of arguments."),
-                None => (),
-            }
-        },
-        Some(tt) => abort!(tt.span(), "Expected end of arguments."),
+        Some(TokenTree::Punct(p)) if p.as_char() == ',' => (),
+        Some(tt) => abort!(tt.span(), "Expected , or end of arguments."),
         None => (),
     };
 
-    (path, color, Some(fields))
+    (path, color, Some(fields), tokens.collect())
+}
+
+/// Separate an optional caller path, used by macros producing a single value.
+///
+/// Returns the path, the color and the trailing options.
+/// Fields are not supported since there is only one value.
+pub fn extract_optional_path(tokens: TokenStream) -> (Option<TokenStream>, TokenStream, TokenStream) {
+    let mut segments = vec![Vec::new()];
+    for tt in tokens {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == ',' && !segments.last().is_some_and(|s| is_option(s)) => {
+                segments.push(Vec::new())
+            },
+            tt => segments.last_mut().unwrap().push(tt),
+        }
+    }
+    let options = match segments.iter().position(|s| is_option(s)) {
+        Some(index) => segments.split_off(index).into_iter().flatten().collect(),
+        None => TokenStream::new(),
+    };
+    if segments.last().is_some_and(|s| s.is_empty()) {
+        segments.pop();
+    }
+    let mut segments = segments.into_iter().map(|s| s.into_iter().collect::<TokenStream>());
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(color), None, _) if !color.is_empty() => (None, color, options),
+        (Some(path), Some(color), None) if !color.is_empty() => {
+            if color.clone().into_iter().any(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == '=')) {
+                abort!(color, "Fields are not supported for packed colors.")
+            }
+            (Some(path), color, options)
+        },
+        (_, _, Some(tt)) => abort!(tt, "Expected end of arguments."),
+        _ => abort!(Span::call_site(), "Expected color token tree."),
+    }
+}
+
+/// A color of `palette!`, optionally named like `Primary = "#1A73E8"`.
+/// A color that is a single identifier like `Red` is named by it.
+pub struct PaletteEntry {
+    pub name: Option<Ident>,
+    pub color: TokenStream,
+}
+
+/// Separate `path [=> {fields}]; colors, ... [; options]` of `palette!`.
+///
+/// Returns the path, the fields, the colors and the options.
+pub fn extract_palette(tokens: TokenStream) -> (TokenStream, Option<TokenStream>, Vec<PaletteEntry>, TokenStream) {
+    let mut sections = vec![Vec::new()];
+    for tt in tokens {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == ';' => sections.push(Vec::new()),
+            tt => sections.last_mut().unwrap().push(tt),
+        }
+    }
+    let mut sections = sections.into_iter();
+    let (head, colors, options) = match (sections.next(), sections.next(), sections.next(), sections.next()) {
+        (Some(head), Some(colors), options, None) => (head, colors, options.unwrap_or_default()),
+        (_, None, _, _) | (None, _, _, _) => abort!(Span::call_site(), "Expected `;` between the path and the colors."),
+        (_, _, _, Some(_)) => abort!(Span::call_site(), "Expected at most 3 sections separated by `;`."),
+    };
+    let (path, fields): (TokenStream, _) = match head.iter().position(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == '=' && p.spacing() == Spacing::Joint)) {
+        None => (head.into_iter().collect(), None),
+        Some(index) => match &head[index + 1..] {
+            [TokenTree::Punct(b), TokenTree::Group(g)] if b.as_char() == '>' && g.delimiter() == Delimiter::Brace => {
+                (head[..index].iter().cloned().collect(), Some(g.stream()))
+            },
+            _ => abort!(head[index].span(), "Expected => {fields}."),
+        },
+    };
+    if path.is_empty() {
+        abort!(Span::call_site(), "Expected path.")
+    }
+    let mut entries = Vec::new();
+    let mut segment = Vec::new();
+    let mut iter = colors.into_iter().peekable();
+    while iter.peek().is_some() {
+        for tt in iter.by_ref() {
+            match tt {
+                TokenTree::Punct(p) if p.as_char() == ',' => break,
+                tt => segment.push(tt),
+            }
+        }
+        let entry = match std::mem::take(&mut segment).as_slice() {
+            [] => abort!(Span::call_site(), "Expected color token tree."),
+            [TokenTree::Ident(name)] => PaletteEntry { name: Some(name.clone()), color: quote!(#name) },
+            segment if is_option(segment) => match segment {
+                [TokenTree::Ident(name), _, color @ ..] if !color.is_empty() => {
+                    PaletteEntry { name: Some(name.clone()), color: color.iter().cloned().collect() }
+                },
+                _ => abort!(segment[0].span(), "Expected color after {} =.", segment[0]),
+            },
+            segment => PaletteEntry { name: None, color: segment.iter().cloned().collect() },
+        };
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        abort!(Span::call_site(), "Expected colors.")
+    }
+    (path, fields, entries, options.into_iter().collect())
 }
 
 #[cfg(test)]
 mod test {
-    use super::extract_path;
+    use super::{extract_path, extract_optional_path, extract_palette};
     use quote::quote;
     use std::mem::discriminant;
 
@@ -116,16 +179,47 @@ mod test {
 
     #[test]
     pub fn test_path_extractor() {
-        let (left, right, _) = extract_path(quote!(Color, [1234, 567]));
+        let (left, right, _, _) = extract_path(quote!(Color, [1234, 567]));
         tokenstream_eq!(left, quote!(Color));
-        assert!(matches!(right, proc_macro2::TokenTree::Group(_)));
+        assert!(matches!(right.into_iter().next(), Some(proc_macro2::TokenTree::Group(_))));
         
-        let (left, right, _) = extract_path(quote!(rgba::Color, 0xFFFFFF));
+        let (left, right, _, _) = extract_path(quote!(rgba::Color, 0xFFFFFF));
         tokenstream_eq!(left, quote!(rgba::Color));
-        assert!(matches!(right, proc_macro2::TokenTree::Literal(_)));
+        assert!(matches!(right.into_iter().next(), Some(proc_macro2::TokenTree::Literal(_))));
 
-        let (left, right, _) = extract_path(quote!(::some::path::func, Red));
+        let (left, right, _, _) = extract_path(quote!(::some::path::func, Red));
         tokenstream_eq!(left, quote!(::some::path::func));
-        assert!(matches!(right, proc_macro2::TokenTree::Ident(_)));
+        assert!(matches!(right.into_iter().next(), Some(proc_macro2::TokenTree::Ident(_))));
+
+        let (left, right, fields, _) = extract_path(quote!(Color::new, hsl[210, 0.4, 0.5] => {r, g, b}));
+        tokenstream_eq!(left, quote!(Color::new));
+        tokenstream_eq!(right, quote!(hsl[210, 0.4, 0.5]));
+        tokenstream_eq!(fields.unwrap(), quote!(r, g, b));
+
+        let (left, fields, entries, options) = extract_palette(quote!(Color::new => {r, g, b}; Red, Accent = "#00FF00", [0.2; 3]; order = bgr));
+        tokenstream_eq!(left, quote!(Color::new));
+        tokenstream_eq!(fields.unwrap(), quote!(r, g, b));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].name.as_ref().unwrap().to_string(), "Accent");
+        tokenstream_eq!(entries[1].color.clone(), quote!("#00FF00"));
+        assert!(entries[2].name.is_none());
+        tokenstream_eq!(options, quote!(order = bgr));
+
+        let (left, right, _) = extract_optional_path(quote!([255, 0, 0]));
+        assert!(left.is_none());
+        tokenstream_eq!(right, quote!([255, 0, 0]));
+
+        let (left, right, _) = extract_optional_path(quote!(Color::from_u32, Red));
+        tokenstream_eq!(left.unwrap(), quote!(Color::from_u32));
+        tokenstream_eq!(right, quote!(Red));
+
+        let (left, right, options) = extract_optional_path(quote!(Red, order = bgra,));
+        assert!(left.is_none());
+        tokenstream_eq!(right, quote!(Red));
+        tokenstream_eq!(options, quote!(order = bgra,));
+
+        let (_, right, _, options) = extract_path(quote!(Color, Red => {r, g, b}, order = bgr));
+        tokenstream_eq!(right, quote!(Red));
+        tokenstream_eq!(options, quote!(order = bgr));
     }
 }
@@ -0,0 +1,214 @@
+use std::path::Path;
+use proc_macro2::Span;
+use proc_macro_error::abort;
+use serde_json::{Map, Value};
+use crate::config::config;
+use crate::parse::{parse_str, ColorResult};
+use colorthis_core::{space, Color};
+
+/// A color token in a W3C design tokens file.
+#[derive(Debug)]
+pub(crate) struct ColorToken {
+    /// Keys of the groups and the token, like `["color", "brand", "primary"]`.
+    pub path: Vec<String>,
+    pub color: ColorResult,
+}
+
+/// Read color tokens from a W3C design tokens JSON file, aliases are resolved
+/// and tokens that are not colors are skipped.
+pub(crate) fn read_tokens(path: &Path, span: Span) -> Vec<ColorToken> {
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => abort!(span, "Cannot read {}: {}.", path.display(), e),
+    };
+    let root: Value = match serde_json::from_str(&src) {
+        Ok(root) => root,
+        Err(e) => abort!(span, "{}: {}", path.display(), e),
+    };
+    let mut reader = Reader { root: &root, file: path, span, tokens: Vec::new() };
+    match &root {
+        Value::Object(group) => reader.group(group, &mut Vec::new(), None),
+        _ => abort!(span, "{}: expected a JSON object.", path.display()),
+    }
+    reader.tokens
+}
+
+struct Reader<'t> {
+    root: &'t Value,
+    file: &'t Path,
+    span: Span,
+    tokens: Vec<ColorToken>,
+}
+
+impl<'t> Reader<'t> {
+    fn error(&self, key: &[String], msg: impl std::fmt::Display) -> ! {
+        abort!(self.span, "{}: {}: {}", self.file.display(), key.join("."), msg)
+    }
+
+    /// Visit a group, `$type` is inherited by nested groups and tokens.
+    fn group(&mut self, group: &'t Map<String, Value>, key: &mut Vec<String>, ty: Option<&'t str>) {
+        let ty = match group.get("$type") {
+            Some(Value::String(ty)) => Some(ty.as_str()),
+            Some(_) => self.error(key, "$type must be a string."),
+            None => ty,
+        };
+        for (name, value) in group {
+            if name.starts_with('$') {
+                continue;
+            }
+            key.push(name.clone());
+            match value {
+                Value::Object(obj) if obj.contains_key("$value") => self.token(obj, key, ty),
+                Value::Object(obj) => self.group(obj, key, ty),
+                _ => self.error(key, "expected a group or a token with $value."),
+            }
+            key.pop();
+        }
+    }
+
+    fn token(&mut self, token: &'t Map<String, Value>, key: &[String], ty: Option<&'t str>) {
+        let (value, resolved) = self.resolve(token, key, &mut Vec::new());
+        let ty = match token.get("$type") {
+            Some(Value::String(ty)) => Some(ty.as_str()),
+            Some(_) => self.error(key, "$type must be a string."),
+            None => ty.or(resolved),
+        };
+        if ty != Some("color") {
+            return;
+        }
+        let color = self.color(value, key);
+        self.tokens.push(ColorToken { path: key.to_vec(), color });
+    }
+
+    /// Follow aliases like `{color.brand.primary}`, returns the value and the `$type` of the target.
+    fn resolve(&self, token: &'t Map<String, Value>, key: &[String], seen: &mut Vec<String>) -> (&'t Value, Option<&'t str>) {
+        let value = &token["$value"];
+        let Some(alias) = value.as_str().and_then(|s| s.strip_prefix('{')).and_then(|s| s.strip_suffix('}')) else {
+            let ty = token.get("$type").and_then(Value::as_str);
+            return (value, ty)
+        };
+        if seen.iter().any(|x| x == alias) {
+            self.error(key, format_args!("circular alias {{{}}}.", alias))
+        }
+        seen.push(alias.to_owned());
+        let mut target = self.root;
+        // `$type` inherited from the groups of the target.
+        let mut group_ty = None;
+        for name in alias.split('.') {
+            group_ty = target.get("$type").and_then(Value::as_str).or(group_ty);
+            target = match target.get(name) {
+                Some(t) => t,
+                None => self.error(key, format_args!("alias {{{}}} not found.", alias)),
+            };
+        }
+        match target {
+            Value::Object(obj) if obj.contains_key("$value") => {
+                let (value, ty) = self.resolve(obj, key, seen);
+                (value, obj.get("$type").and_then(Value::as_str).or(group_ty).or(ty))
+            },
+            _ => self.error(key, format_args!("alias {{{}}} is not a token.", alias)),
+        }
+    }
+
+    /// Parse a string with the color syntax, or a color object with `colorSpace` and `components`.
+    fn color(&self, value: &Value, key: &[String]) -> ColorResult {
+        let css = match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Object(obj) => self.color_object(obj, key),
+            _ => self.error(key, "expected a color string or object."),
+        };
+        let color = match css {
+            Ok(css) => match parse_str(&css) {
+                Ok(color) => color,
+                Err(err) => self.error(key, format_args!("invalid color {:?}: {}", css, err)),
+            },
+            Err(color) => Color::Floats(color),
+        };
+        match color.check(config().check()) {
+            Ok(color) => color.into(),
+            Err(err) => self.error(key, err),
+        }
+    }
+
+    /// Convert a color object to CSS syntax, or a color directly for `srgb-linear`.
+    fn color_object(&self, obj: &Map<String, Value>, key: &[String]) -> Result<String, [f64; 4]> {
+        let alpha = match obj.get("alpha") {
+            None => 1.0,
+            Some(a) => match a.as_f64() {
+                Some(a) => a,
+                None => self.error(key, "alpha must be a number."),
+            },
+        };
+        let space = obj.get("colorSpace").and_then(Value::as_str);
+        let components = match obj.get("components") {
+            Some(Value::Array(c)) if c.len() == 3 => c.iter().map(|x| match x {
+                Value::Number(n) => n.as_f64().map(|x| x.to_string()),
+                Value::String(s) if s == "none" => Some("none".to_owned()),
+                _ => None,
+            }).collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        let Some(components) = components else {
+            self.error(key, "components must be an array of 3 numbers.")
+        };
+        let [x, y, z] = [&components[0], &components[1], &components[2]];
+        Ok(match space {
+            Some("srgb") => {
+                let [x, y, z] = [x, y, z].map(|c| c.parse::<f64>().map(|c| format!("{}%", c * 100.0)).unwrap_or("none".to_owned()));
+                format!("rgb({} {} {} / {})", x, y, z, alpha)
+            },
+            Some("srgb-linear") => {
+                let [r, g, b] = space::snap([x, y, z].map(|c| space::srgb_encode(c.parse().unwrap_or(0.0))));
+                return Err([r, g, b, alpha])
+            },
+            Some(space @ ("hsl" | "hwb" | "lab" | "lch" | "oklab" | "oklch")) => {
+                format!("{}({} {} {} / {})", space, x, y, z, alpha)
+            },
+            _ => match obj.get("hex").and_then(Value::as_str) {
+                Some(hex) if alpha < 1.0 => format!("{}{:02X}", hex, (alpha * 255.0).round() as u8),
+                Some(hex) => hex.to_owned(),
+                None => self.error(key, format_args!("unsupported colorSpace {}.", space.unwrap_or("null"))),
+            }
+        })
+    }
+}
+
+/// Convert a group key like `Brand Colors` to a module name like `brand_colors`.
+pub(crate) fn module_name(key: &str) -> Option<String> {
+    let words: Vec<_> = key.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_ascii_lowercase())
+        .collect();
+    if words.is_empty() {
+        return None
+    }
+    let mut name = words.join("_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    Some(name)
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "try", "typeof", "unsized", "virtual", "yield", "gen",
+];
+
+#[cfg(test)]
+mod test {
+    use super::module_name;
+
+    #[test]
+    pub fn test_module_name() {
+        assert_eq!(module_name("Brand Colors").unwrap(), "brand_colors");
+        assert_eq!(module_name("type").unwrap(), "type_");
+        assert_eq!(module_name("100").unwrap(), "_100");
+        assert_eq!(module_name("--"), None);
+    }
+}
@@ -1,7 +1,9 @@
 // This simulates for bevy intergration
+#![allow(clippy::excessive_precision)]
 
 /// A 1-1 copy of Bevy's Color.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[allow(dead_code)]
 enum Color {
     Rgba {
         red: f32,
@@ -45,6 +47,18 @@ macro_rules! rgba {
     };
 }
 
+macro_rules! rgba_linear {
+    ($color: tt) => {
+        ::colorthis::rgba_linear!(Color::RgbaLinear, $color => {red, green, blue, alpha})
+    };
+}
+
+macro_rules! hsla {
+    ($($color: tt)+) => {
+        ::colorthis::hsla!(Color::Hsla, $($color)+ => {hue, saturation, lightness, alpha})
+    };
+}
+
 const F1: f32 = 1.0 / 255.0;
 const F2: f32 = 2.0 / 255.0;
 const F3: f32 = 3.0 / 255.0;
@@ -72,5 +86,39 @@ pub fn test_bevy(){
     assert_eq!(rgba!(Gray700), Color::rgba(55.0/255.0, 65.0/255.0, 81.0/255.0, 255.0/255.0));
     assert_eq!(rgba!(Indigo50), Color::rgba(238.0/255.0, 242.0/255.0, 255.0/255.0, 255.0/255.0));
     assert_eq!(rgba!(Fuchsia300), Color::rgba(240.0/255.0, 171.0/255.0, 252.0/255.0, 255.0/255.0));
-    
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn test_bevy_hsla(){
+    assert_eq!(hsla!(Red), Color::Hsla { hue: 0.0, saturation: 1.0, lightness: 0.5, alpha: 1.0 });
+    assert_eq!(hsla!("#00FFFF80"), Color::Hsla { hue: 180.0, saturation: 1.0, lightness: 0.5, alpha: 128.0 / 255.0 });
+    assert_eq!(hsla!(hsl[240, 0.5, 0.25]), Color::Hsla { hue: 240.0, saturation: 0.5, lightness: 0.25, alpha: 1.0 });
+}
+
+#[test]
+pub fn test_bevy_linear(){
+    assert_eq!(rgba_linear!(White), Color::RgbaLinear { red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0 });
+    assert_eq!(rgba_linear!("#00FF0000"), Color::RgbaLinear { red: 0.0, green: 1.0, blue: 0.0, alpha: 0.0 });
+}
+
+mod derived {
+    use colorthis::ColorThis;
+
+    #[derive(Debug, PartialEq, Clone, Copy, ColorThis)]
+    #[allow(dead_code)]
+    pub enum Color {
+        #[colorthis(macro = derived_rgba)]
+        Rgba { red: f32, green: f32, blue: f32, alpha: f32 },
+        #[colorthis(macro = derived_linear, linear)]
+        RgbaLinear { red: f32, green: f32, blue: f32, alpha: f32 },
+        Hsla { hue: f32, saturation: f32, lightness: f32, alpha: f32 },
+    }
+
+    #[test]
+    pub fn test_bevy_derive(){
+        assert_eq!(derived_rgba!(1, 2, 3), Color::Rgba { red: super::F1, green: super::F2, blue: super::F3, alpha: 1.0 });
+        assert_eq!(derived_rgba!("#01020304"), Color::Rgba { red: super::F1, green: super::F2, blue: super::F3, alpha: super::F4 });
+        assert_eq!(derived_rgba!(Red / 50), Color::Rgba { red: 1.0, green: 0.0, blue: 0.0, alpha: 0.5 });
+        assert_eq!(derived_linear!(White), Color::RgbaLinear { red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0 });
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::excessive_precision)]
+
 
 use colorthis::*;
 
@@ -168,6 +170,22 @@ pub fn test_clamp(){
 }
 
 
+#[test]
+#[cfg(all(feature="unchecked", not(feature="clamp")))]
+pub fn test_unchecked_channel_type(){
+    assert_eq!(rgba!(array4, [300, -1, 0, 255], ty = u8), [255u8, 0, 0, 255]);
+    assert_eq!(rgba!(array4, [1.5, -0.5, 0.0, 1.0], ty = u16), [65535u16, 0, 0, 65535]);
+    assert_eq!(rgba_u32!([300, -1, 0]), 0xFF0000FF);
+}
+
+#[test]
+#[cfg(all(feature="unchecked", not(feature="clamp")))]
+pub fn test_unchecked_untyped(){
+    assert_eq!(rgb!(array3, [1.5, 0.0, -0.5], round = trunc, scale = 255), [382, 0, -127]);
+    assert_eq!(rgb!(array3, [300, 0, -5]), [300, 0, -5]);
+}
+
+
 #[test]
 #[cfg(feature="compact")]
 pub fn test_compact(){
@@ -177,4 +195,573 @@ pub fn test_compact(){
     assert_eq!(rgba!(Color::new, "1234"), Color::new(0x11, 0x22, 0x33, 0x44));
     assert_eq!(rgba!(Color::new, "#123"), Color::new(0x11, 0x22, 0x33, 255));
     assert_eq!(rgba!(Color::new, "#1234"), Color::new(0x11, 0x22, 0x33, 0x44));
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn test_css_rgb(){
+    assert_eq!(rgba!(Color::new, "rgb(1, 2, 3)"), Color::new(1, 2, 3, 255));
+    assert_eq!(rgba!(Color::new, "rgba(1, 2, 3, 0)"), Color::new(1, 2, 3, 0));
+    assert_eq!(rgba!(Color::new, "rgb(1 2 3)"), Color::new(1, 2, 3, 255));
+    assert_eq!(rgba!(Color::new, "RGB(255 none 0 / 0%)"), Color::new(255, 0, 0, 0));
+    assert_eq!(rgba!(Color::new, "rgb(100% 0% 100%)"), Color::new(255, 0, 255, 255));
+    assert_eq!(rgba!(Color::new, "rgba(100%, 0%, 100%, 1)"), Color::new(255, 0, 255, 255));
+    assert_eq!(rgb!(Color3::new, "rgb(12 200 40 / 50%)", round = trunc, scale = 255), Color3::new(12, 200, 40));
+    assert_eq!(rgbaf!(ColorF::new, "rgb(1 2 3)"), ColorF::new(F1, F2, F3, 1.0));
+    assert_eq!(rgbaf!(ColorF::new, "rgb(1 2 3 / .25)"), ColorF::new(F1, F2, F3, 0.25));
+    assert_eq!(rgbaf!(ColorF::new, "rgba(1, 2, 3, 25%)"), ColorF::new(F1, F2, F3, 0.25));
+    assert_eq!(rgbf!(Color3F::new, "rgb(0% 50% 100%)"), Color3F::new(0.0, 0.5, 1.0));
+}
+
+
+#[test]
+pub fn test_hsl_hwb(){
+    assert_eq!(rgba!(Color::new, "hsl(0 100% 50%)"), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, "hsla(240, 100%, 50%, 0)"), Color::new(0, 0, 255, 0));
+    assert_eq!(rgba!(Color::new, hsl[0, 1, 0.5]), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, "hwb(0 0% 0%)"), Color::new(255, 0, 0, 255));
+    assert_eq!(rgbf!(Color3F::new, "hsl(210deg 40% 50%)"), Color3F::new(0.3, 0.5, 0.7));
+    assert_eq!(rgbf!(Color3F::new, "hsl(210 40 50)"), Color3F::new(0.3, 0.5, 0.7));
+    assert_eq!(rgbf!(Color3F::new, hsl[210, 0.4, 0.5]), Color3F::new(0.3, 0.5, 0.7));
+    assert_eq!(rgbf!(Color3F::new, hsl[210deg, 40%, 50%]), Color3F::new(0.3, 0.5, 0.7));
+    assert_eq!(rgbaf!(ColorF::new, "hsl(0.5turn 100% 50% / 0.5)"), ColorF::new(0.0, 1.0, 1.0, 0.5));
+    assert_eq!(rgbaf!(ColorF::new, hsla[0.5turn, 1.0, 0.5, 50%]), ColorF::new(0.0, 1.0, 1.0, 0.5));
+    assert_eq!(rgbaf!(ColorF::new, hsl[-200grad, 1.0, 0.5]), ColorF::new(0.0, 1.0, 1.0, 1.0));
+    assert_eq!(rgbf!(Color3F::new, "hwb(90 50% 50%)"), Color3F::new(0.5, 0.5, 0.5));
+    assert_eq!(rgbf!(Color3F::new, hwb[120, 0.2, 0.2]), Color3F::new(0.2, 0.8, 0.2));
+}
+
+
+#[test]
+pub fn test_oklab(){
+    assert_eq!(rgba!(Color::new, "oklab(1 0 0)"), Color::new(255, 255, 255, 255));
+    assert_eq!(rgba!(Color::new, "oklab(0% 0 0 / 50%)", round = trunc, scale = 255), Color::new(0, 0, 0, 127));
+    assert_eq!(rgba!(Color::new, "oklch(0.6279554 0.2576833 29.2338851)"), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, "oklch(70% 25% 250deg)", round = trunc, scale = 255), Color::new(108, 163, 218, 255));
+    assert_eq!(rgba!(Color::new, oklch[0.7, 0.1, 250], round = trunc, scale = 255), Color::new(108, 163, 218, 255));
+    assert_eq!(rgba!(Color::new, oklab[0.7, -0.0342, -0.094], round = trunc, scale = 255), Color::new(108, 163, 218, 255));
+    assert_eq!(rgba!(Color::new, "oklab(50% 25% -0.1)", round = trunc, scale = 255), Color::new(128, 69, 153, 255));
+}
+
+#[test]
+#[cfg(not(feature="strict-gamut"))]
+pub fn test_gamut_map(){
+    assert_eq!(rgba!(Color::new, "oklch(0.7 0.4 30)"), Color::new(255, 88, 67, 255));
+    assert_eq!(rgba!(Color::new, oklch[0.7, 0.4, 30]), Color::new(255, 88, 67, 255));
+    assert_eq!(rgbf!(Color3F::new, oklch[1.5, 0.2, 0]), Color3F::new(1.0, 1.0, 1.0));
+}
+
+
+#[test]
+pub fn test_lab(){
+    assert_eq!(rgbaf!(ColorF::new, "lab(52.2% 40.1 59.9)"), ColorF::new(0.775408148765564, 0.36330851912498474, 0.025650829076766968, 1.0));
+    assert_eq!(rgbaf!(ColorF::new, lab[52.2, 40.1, 59.9, 0.5]), ColorF::new(0.775408148765564, 0.36330851912498474, 0.025650829076766968, 0.5));
+    assert_eq!(rgba!(Color::new, "lab(100% 0 0)"), Color::new(255, 255, 255, 255));
+    assert_eq!(rgba!(Color::new, "lab(0 none none / 0)"), Color::new(0, 0, 0, 0));
+    assert_eq!(rgba!(Color::new, "lch(29.69 66.83 309.47)", round = trunc, scale = 255), Color::new(97, 39, 152, 255));
+    assert_eq!(rgba!(Color::new, lch[29.69, 66.83, 309.47deg], round = trunc, scale = 255), Color::new(97, 39, 152, 255));
+}
+
+
+#[test]
+pub fn test_output_spaces(){
+    assert_eq!(hsla!(ColorF::new, Red), ColorF::new(0.0, 1.0, 0.5, 1.0));
+    assert_eq!(hsla!(ColorF::new, "hsl(210 40% 50% / 0.5)"), ColorF::new(210.0, 0.4, 0.5, 0.5));
+    assert_eq!(hsla!(ColorF::new, [0, 0, 0, 0]), ColorF::new(0.0, 0.0, 0.0, 0.0));
+    assert_eq!(hsva!(ColorF::new, Red), ColorF::new(0.0, 1.0, 1.0, 1.0));
+    assert_eq!(hsva!(ColorF::new, [0.5; 3]), ColorF::new(0.0, 0.0, 0.5, 1.0));
+    assert_eq!(oklcha!(ColorF::new, White), ColorF::new(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(oklcha!(ColorF::new, "#00000000"), ColorF::new(0.0, 0.0, 0.0, 0.0));
+    assert_eq!(laba!(ColorF::new, White), ColorF::new(100.0, 0.0, 0.0, 1.0));
+    assert_eq!(laba!(ColorF::new, "lab(52.2% 40.1 59.9)"), ColorF::new(52.2, 40.1, 59.9, 1.0));
+    assert_eq!(lcha!(ColorF::new, "lch(29.69 66.83 309.47)"), ColorF::new(29.69, 66.83, 309.47, 1.0));
+    assert_eq!(lcha!(ColorF, Black => {r, g, b, a}), ColorF::new(0.0, 0.0, 0.0, 1.0));
+}
+
+
+#[test]
+pub fn test_linear(){
+    assert_eq!(rgb_linear!(Color3F::new, Black), Color3F::new(0.0, 0.0, 0.0));
+    assert_eq!(rgb_linear!(Color3F::new, "#FF8080"), Color3F::new(1.0, 0.2158605009317398, 0.2158605009317398));
+    assert_eq!(rgba_linear!(ColorF::new, [0.5; 3, 0.5]), ColorF::new(0.21404114365577698, 0.21404114365577698, 0.21404114365577698, 0.5));
+    assert_eq!(rgba_linear!(ColorF::new, "#01FFFF80"), ColorF::new(0.0003035269910469651, 1.0, 1.0, 128.0 / 255.0));
+}
+
+
+#[test]
+pub fn test_premultiplied(){
+    #[cfg(not(feature="scale-256"))]
+    assert_eq!(rgba_premul!(Color::new, [200, 100, 50, 128]), Color::new(100, 50, 25, 128));
+    #[cfg(not(feature="scale-256"))]
+    assert_eq!(rgba_premul!(Color::new, "#FF000080"), Color::new(128, 0, 0, 128));
+    assert_eq!(rgba_premul!(Color::new, [1.0, 0.5, 0.0, 0.5]), Color::new(128, 64, 0, 128));
+    assert_eq!(rgba_premul!(Color::new, [200, 200, 200, 200], scale = 255), Color::new(157, 157, 157, 200));
+    #[cfg(not(feature="scale-256"))]
+    assert_eq!(rgba_premul!(Color::new, (200u8, 100, 50, 128)), Color::new(100, 50, 25, 128));
+    assert_eq!(rgba_premul!(Color::new, [200, 100, 50, 128], round = trunc, scale = 255), Color::new(100, 50, 25, 128));
+    assert_eq!(rgba_premul!(Color::new, "#FF000080", round = trunc, scale = 255), Color::new(128, 0, 0, 128));
+    assert_eq!(rgba_premul!(Color::new, [1.0, 0.5, 0.0, 0.5], round = trunc, scale = 255), Color::new(127, 63, 0, 127));
+    assert_eq!(rgba_premul!(Color::new, [1.0, 0.5, 0.0, 0.5], round = half_up), Color::new(128, 64, 0, 128));
+    assert_eq!(rgba_premul!(Color::new, [200, 100, 50, 128], round = half_up, scale = 256), Color::new(101, 50, 25, 128));
+    assert_eq!(rgba_premul!(array4, [1.0, 0.5, 0.0, 0.5], ty = u16, round = trunc, scale = 255), [32767u16, 16383, 0, 32767]);
+    assert_eq!(rgba_premul!(Color::new, Red), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba_premul!(Color::new, (200u8, 100, 50, 128), round = trunc, scale = 255), Color::new(100, 50, 25, 128));
+    assert_eq!(rgba_premul!(Color::new, (200u8, 100, 50, 128), round = half_up, scale = 256), Color::new(101, 50, 25, 128));
+    assert_eq!(rgba_premul!(Color::new, (1u8, 3, 5, 127), round = half_even, scale = 256), Color::new(0, 2, 2, 127));
+    assert_eq!(rgba_premul!(Color::new, [1, 3, 5, 127], round = half_even, scale = 256), Color::new(0, 2, 2, 127));
+    assert_eq!(rgba_premul!(Color::new, (1u8, 3, 5, 127), round = half_up, scale = 256), Color::new(1, 2, 3, 127));
+    assert_eq!(rgba_premul!(Color::new, (200, 100, 50)), Color::new(200, 100, 50, 255));
+    assert_eq!(rgba_premul!(Color::new, (255u8, 255, 255, 100), round = half_up, scale = 256), Color::new(100, 100, 100, 100));
+    assert_eq!(rgbaf_premul!(ColorF::new, [1.0, 0.5, 0.25, 0.5]), ColorF::new(0.5, 0.25, 0.125, 0.5));
+    assert_eq!(rgbaf_premul!(ColorF::new, "#FFFFFF00"), ColorF::new(0.0, 0.0, 0.0, 0.0));
+    let alpha = 0.5;
+    assert_eq!(rgbaf_premul!(ColorF::new, (1.0, 0.5, 0.25, alpha)), ColorF::new(0.5, 0.25, 0.125, 0.5));
+    assert_eq!(rgbaf_premul!(ColorF, (1.0, 0.5, 0.25, alpha) => {r, g, b, a}), ColorF::new(0.5, 0.25, 0.125, 0.5));
+}
+
+
+#[test]
+pub fn test_packed(){
+    assert_eq!(rgba_u32!("#FF8000"), 0xFF8000FFu32);
+    assert_eq!(argb_u32!("#FF800040"), 0x40FF8000);
+    assert_eq!(abgr_u32!(Red), 0xFF0000FF);
+    assert_eq!(bgra_u32!([0x11, 0x22, 0x33, 0x44]), 0x33221144);
+    assert_eq!(rgba_u32!([1.0, 0.5, 0.0], round = trunc, scale = 255), 0xFF7F00FF);
+    assert_eq!(rgb565!("#FF8000", round = trunc, scale = 255), 0xFBE0u16);
+    assert_eq!(rgb565!(White), 0xFFFF);
+    assert_eq!(rgba4444!("#FF800080", round = trunc, scale = 255), 0xF707u16);
+    assert_eq!(rgba_u32!(u32::swap_bytes, "#11223344"), 0x44332211);
+    let (r, g, b) = (0x11u8, 0x22u8, 0x33u8);
+    assert_eq!(argb_u32!((r, g, b)), 0xFF112233);
+    assert_eq!(rgb565!((255, 128, 0), round = trunc, scale = 255), 0xFBE0);
+    assert_eq!(rgb565!((255, 128, 0), round = half_up, scale = 255), 0xFC00);
+    let (r, g, b) = (200u8, 130u8, 3u8);
+    assert_eq!(rgb565!((r, g, b), round = half_up, scale = 255), rgb565!([200, 130, 3], round = half_up, scale = 255));
+    assert_eq!(rgb565!((r, g, b), round = half_even, scale = 256), rgb565!([200, 130, 3], round = half_even, scale = 256));
+    assert_eq!(rgba4444!((r, g, b, b), round = floor, scale = 256), rgba4444!([200, 130, 3, 3], round = floor, scale = 256));
+    assert_eq!(rgb565!((255, 255, 255), round = half_up, scale = 256), 0xFFFF);
+    const PACKED: u32 = rgba_u32!(hsl[0, 1.0, 0.5]);
+    assert_eq!(PACKED, 0xFF0000FF);
+}
+
+#[test]
+pub fn test_packed_layout(){
+    assert_eq!(rgb565!("#FF0080", order = bgr, round = trunc, scale = 255), 0x781F);
+    assert_eq!(rgb565!("#FF0080", order = bgr, round = trunc, scale = 255), packed!("#FF0080", layout = b5g6r5, round = trunc, scale = 255));
+    assert_eq!(rgba4444!("#11223344", order = bgra), 0x3214);
+    assert_eq!(argb_u32!("#11223344", order = bgra), bgra_u32!("#11223344"));
+    assert_eq!(packed!(u32::swap_bytes, "#11223344", layout = r8g8b8a8), 0x44332211);
+    assert_eq!(packed!(Red, layout = a2r10g10b10, scale = 255), 0xFFF00000u32);
+    assert_eq!(packed!(Blue, layout = a2r10g10b10, order = argb, scale = 255), 0xC00003FF);
+    assert_eq!(packed!(White, layout = r3g3b2), 0xFFu8);
+    assert_eq!(packed!("#FF0000", layout = r5g5b5), 0x7C00u16);
+    assert_eq!(packed!([1.0, 0.5, 0.0, 1.0], layout = r16g16b16a16, round = trunc, scale = 255), 0xFFFF_7FFF_0000_FFFFu64);
+    let (r, g, b) = (255u8, 0u8, 255u8);
+    assert_eq!(packed!((r, g, b), layout = a2r10g10b10, scale = 255), 0xFFF003FF);
+    assert_eq!(packed!((r, g, b, 0), layout = r16g16b16a16, scale = 255), 0xFFFF_0000_FFFF_0000u64);
+    assert_eq!(packed!((r, g, b), layout = r3g3b2), 0xE3u8);
+}
+
+
+#[test]
+pub fn test_channel_order(){
+    assert_eq!(rgba!(Color::new, "#11223344", order = bgra), Color::new(0x33, 0x22, 0x11, 0x44));
+    assert_eq!(rgba!(Color::new, "#11223344", order = argb,), Color::new(0x44, 0x11, 0x22, 0x33));
+    assert_eq!(rgb!(Color3::new, "#112233", order = bgr), Color3::new(0x33, 0x22, 0x11));
+    assert_eq!(rgb!(Color3, "#112233" => {r, g, b}, order = bgr), Color3::new(0x33, 0x22, 0x11));
+    assert_eq!(rgbaf!(ColorF::new, [1.0, 0.5, 0.0], order = abgr), ColorF::new(1.0, 0.0, 0.5, 1.0));
+    assert_eq!(hsla!(ColorF::new, Red, order = lsha), ColorF::new(0.5, 1.0, 0.0, 1.0));
+    let (r, g, b) = (1u8, 2u8, 3u8);
+    assert_eq!(rgba!(Color::new, (r, g, b), order = bgra), Color::new(3, 2, 1, 255));
+    assert_eq!(rgba_u32!(Red, ), 0xFF0000FF);
+}
+
+
+fn array3<T>(r: T, g: T, b: T) -> [T; 3] {
+    [r, g, b]
+}
+
+fn array4<T>(r: T, g: T, b: T, a: T) -> [T; 4] {
+    [r, g, b, a]
+}
+
+#[test]
+pub fn test_channel_type(){
+    assert_eq!(rgba!(Color::new, "#FF800000", ty = u8), Color::new(255, 128, 0, 0));
+    assert_eq!(rgba!(ColorF::new, "#FF8000", ty = f32), ColorF::new(1.0, 128.0 / 255.0, 0.0, 1.0));
+    assert_eq!(rgbaf!(ColorF::new, [1.0, 0.5, 0.0], ty = f32), ColorF::new(1.0, 0.5, 0.0, 1.0));
+    assert_eq!(rgbaf!(array4, "#FF8000", ty = u16, round = trunc, scale = 255), [65535u16, 32896, 0, 65535]);
+    assert_eq!(rgba!(array4, [1.0, 0.5, 0.0], ty = u16, round = trunc, scale = 255), [65535u16, 32767, 0, 65535]);
+    assert_eq!(rgb!(array3, "#FF8000", ty = i8, round = trunc, scale = 255), [127i8, 63, 0]);
+    assert_eq!(rgb!(array3, "#FF8000", ty = i16, round = trunc, scale = 255), [32767i16, 16447, 0]);
+    assert_eq!(rgb!(array3, White, ty = f64), [1.0f64; 3]);
+    assert_eq!(rgb_linear!(array3, White, ty = u16), [65535u16; 3]);
+    assert_eq!(hsla!(array4, Red, ty = f32), [0.0f32, 1.0, 0.5, 1.0]);
+    assert_eq!(rgba!(array4, "#11223344", order = bgra, ty = u16, round = trunc, scale = 255), [0x3333u16, 0x2222, 0x1111, 0x4444]);
+}
+
+
+#[test]
+pub fn test_rounding(){
+    assert_eq!(rgb!(array3, [0.5, 0.5, 0.5], round = trunc, scale = 255), [127, 127, 127]);
+    assert_eq!(rgb!(array3, [0.5, 0.5, 0.5], round = floor, scale = 255), [127, 127, 127]);
+    assert_eq!(rgb!(array3, [0.5, 0.5, 0.5], round = half_up), [128, 128, 128]);
+    assert_eq!(rgb!(array3, [0.5, 0.5, 0.5], round = half_even), [128, 128, 128]);
+    assert_eq!(rgb!(array3, [0.3, 0.3, 0.3], round = half_even, scale = 255), [76, 76, 76]);
+    assert_eq!(rgb!(array3, [1.0, 0.5, 0.0], scale = 256, round = trunc), [255, 128, 0]);
+    assert_eq!(rgb!(array3, [0.998, 0.002, 0.0], scale = 256, round = floor), [255, 0, 0]);
+    assert_eq!(rgba!(array4, [1.0, 0.5, 0.0, 0.5], ty = u16, round = half_up), [65535u16, 32768, 0, 32768]);
+    assert_eq!(rgba!(array4, "#FF800080", ty = u16, scale = 256, round = trunc), [65280u16, 32768, 0, 32768]);
+    assert_eq!(rgba!(array4, "#FF8000", ty = i8, round = half_up), [127i8, 64, 0, 127]);
+    assert_eq!(rgba!(array4, [0.5; 3, 1.0], round = half_up), [128, 128, 128, 255]);
+    assert_eq!(rgb565!("#FF8000", round = half_up), 0xFC00);
+    assert_eq!(rgba_u32!([1.0, 0.5, 0.0], round = half_even), 0xFF8000FF);
+}
+
+#[test]
+#[cfg(not(any(feature="round-half-even", feature="round-half-up", feature="round-floor", feature="scale-256")))]
+pub fn test_default_rounding(){
+    assert_eq!(rgb!(array3, [0.5, 0.5, 0.5]), [127, 127, 127]);
+    assert_eq!(rgba_u32!([1.0, 0.5, 0.0]), 0xFF7F00FF);
+    assert_eq!(rgb565!("#FF8000"), 0xFBE0);
+}
+
+
+define_colors! {
+    macro brand;
+    BrandPrimary = "#1A73E8",
+    Danger600 = [220, 38, 38],
+    Accent = hsl[210, 0.4, 0.5],
+    override Red = "#EE0000",
+}
+
+#[test]
+pub fn test_custom_colors(){
+    assert_eq!(brand!(rgba!(Color::new, BrandPrimary)), Color::new(0x1A, 0x73, 0xE8, 255));
+    assert_eq!(brand!(rgb!(Color3::new, Danger600)), Color3::new(220, 38, 38));
+    assert_eq!(brand!(rgb!(Color3::new, Accent, round = trunc, scale = 255)), Color3::new(76, 127, 178));
+    assert_eq!(brand!(rgb!(Color3::new, Red)), Color3::new(0xEE, 0, 0));
+    assert_eq!(brand!(rgb!(Color3::new, Blue)), Color3::new(0, 0, 255));
+    assert_eq!(brand!(rgba!(Color::new, BrandPrimary, order = bgra)), Color::new(0xE8, 0x73, 0x1A, 255));
+    assert_eq!(brand!(rgba!(Color::new, BrandPrimary,)), Color::new(0x1A, 0x73, 0xE8, 255));
+    assert_eq!(brand!(rgba_u32!(BrandPrimary)), 0x1A73E8FF);
+    assert_eq!(rgb!(Color3::new, BrandPrimary, colors = {BrandPrimary = "#123456"}), Color3::new(0x12, 0x34, 0x56));
+}
+
+#[test]
+pub fn test_custom_colors_wrapper(){
+    assert_eq!(brand!(colorthis::rgb!(Color3::new, Danger600)), Color3::new(220, 38, 38));
+    assert_eq!(brand!(::colorthis::rgba_u32!(BrandPrimary,)), 0x1A73E8FF);
+    assert_eq!(brand!(palette!(Color3::new; BrandPrimary, Danger600; output = rgb)), [
+        Color3::new(0x1A, 0x73, 0xE8),
+        Color3::new(220, 38, 38),
+    ]);
+    assert_eq!(brand!(palette!(Color::new; BrandPrimary, Red)), [
+        Color::new(0x1A, 0x73, 0xE8, 255),
+        Color::new(0xEE, 0, 0, 255),
+    ]);
+    assert_eq!(brand!(palette!(Color::new; BrandPrimary, Red,)), brand!(palette!(Color::new; BrandPrimary, Red;)));
+    assert_eq!(brand!(gradient!(Color3::new; Red, Danger600; steps = 2, output = rgb, round = trunc, scale = 255,)), [
+        Color3::new(0xEE, 0, 0),
+        Color3::new(220, 38, 38),
+    ]);
+    assert_eq!(brand!(colorthis::gradient!(Color::new; BrandPrimary, BrandPrimary; steps = 2, round = trunc, scale = 255)), [
+        Color::new(0x1A, 0x73, 0xE8, 255),
+        Color::new(0x1A, 0x73, 0xE8, 255),
+    ]);
+}
+
+
+include_palette!(Color, "tests/palettes/test.gpl" => {r, g, b, a}, consts = Color);
+
+#[test]
+pub fn test_palette(){
+    assert_eq!(include_palette!(Color::new, "tests/palettes/test.gpl"), [
+        Color::new(255, 0, 0, 255),
+        Color::new(0, 128, 255, 255),
+        Color::new(17, 34, 51, 255),
+    ]);
+    assert_eq!(include_palette!(Color3, "tests/palettes/test.pal" => {r, g, b}, output = rgb), [
+        Color3::new(255, 0, 0),
+        Color3::new(0, 128, 255),
+    ]);
+    assert_eq!(include_palette!(ColorF::new, "tests/palettes/test.hex", output = rgbaf), [
+        ColorF::new(1.0, 0.0, 0.0, 1.0),
+        ColorF::new(0.0, 128.0 / 255.0, 1.0, 1.0),
+    ]);
+    assert_eq!(include_palette!(Color::new, "tests/palettes/test.txt", order = bgra), [
+        Color::new(0, 0, 255, 255),
+        Color::new(255, 128, 0, 128),
+    ]);
+    assert_eq!(RED, Color::new(255, 0, 0, 255));
+    assert_eq!(SKY_BLUE, Color::new(0, 128, 255, 255));
+    assert_eq!(COLOR_2, Color::new(17, 34, 51, 255));
+}
+
+#[cfg(feature="tokens")]
+mod tokens {
+    use super::*;
+    include_tokens!(Color, "tests/tokens/tokens.json" => {r, g, b, a}, consts = Color, round = trunc, scale = 255);
+}
+
+#[cfg(feature="tokens")]
+mod tokens_f {
+    use super::*;
+    include_tokens!(ColorF, "tests/tokens/tokens.json" => {r, g, b, a}, consts = ColorF, output = rgbaf);
+}
+
+#[cfg(feature="tokens")]
+#[test]
+pub fn test_tokens(){
+    assert_eq!(tokens::color::brand::PRIMARY, Color::new(0x1A, 0x73, 0xE8, 255));
+    assert_eq!(tokens::color::brand::SECONDARY, Color::new(255, 0, 0, 255));
+    assert_eq!(tokens::color::base::RED, Color::new(255, 0, 0, 255));
+    assert_eq!(tokens::color::base::SKY_BLUE, Color::new(0, 127, 255, 127));
+    assert_eq!(tokens::color::base::LINEAR, Color::new(255, 0, 0, 255));
+    assert_eq!(tokens::ACCENT, Color::new(0x1A, 0x73, 0xE8, 255));
+    assert_eq!(tokens_f::color::base::SKY_BLUE, ColorF::new(0.0, 0.5, 1.0, 0.5));
+}
+
+mod swatches {
+    use super::*;
+    include_palette!(Color, "tests/palettes/test.ase" => {r, g, b, a}, consts = Color, round = trunc, scale = 255);
+}
+
+#[test]
+pub fn test_binary_palette(){
+    assert_eq!(swatches::RED, Color::new(255, 0, 0, 255));
+    assert_eq!(swatches::CYAN, Color::new(0, 255, 255, 255));
+    assert_eq!(swatches::LAB_WHITE, Color::new(255, 255, 255, 255));
+    assert_eq!(swatches::GRAY, Color::new(127, 127, 127, 255));
+    assert_eq!(include_palette!(Color::new, "tests/palettes/test.aco", round = trunc, scale = 255), [
+        Color::new(255, 128, 0, 255),
+        Color::new(0, 255, 255, 255),
+        Color::new(255, 255, 255, 255),
+        Color::new(191, 191, 191, 255),
+        Color::new(255, 0, 0, 255),
+    ]);
+}
+
+palette!(Color => {r, g, b, a}; Primary = "#1A73E8", Red, Accent = [0.2; 3]; enum = Brand: Color);
+
+#[test]
+pub fn test_palette_macro(){
+    assert_eq!(palette!(Color::new; Red, "#00FF00", [0.2; 3], Sky400), [
+        Color::new(255, 0, 0, 255),
+        Color::new(0, 255, 0, 255),
+        Color::new(51, 51, 51, 255),
+        rgba!(Color::new, Sky400),
+    ]);
+    const PALETTE: [Color; 2] = palette!(Color => {r, g, b, a}; Red, Blue);
+    assert_eq!(PALETTE[1], Color::new(0, 0, 255, 255));
+    assert_eq!(palette!(Color3::new; Red, Blue; output = rgb, order = bgr), [
+        Color3::new(0, 0, 255),
+        Color3::new(255, 0, 0),
+    ]);
+    assert_eq!(Brand::Primary.color(), Color::new(0x1A, 0x73, 0xE8, 255));
+    assert_eq!(Brand::Accent.color(), Color::new(51, 51, 51, 255));
+    assert_eq!(Brand::ALL, [Brand::Primary, Brand::Red, Brand::Accent]);
+    assert_eq!(Brand::NAMES, ["Primary", "Red", "Accent"]);
+    assert_eq!(Brand::Red.name(), "Red");
+    assert_eq!(Brand::from_name("Accent"), Some(Brand::Accent));
+    assert_eq!(Brand::from_name("Blue"), None);
+}
+
+#[test]
+pub fn test_gradient(){
+    assert_eq!(gradient!(Color::new; Black, White; steps = 3, space = srgb, round = trunc, scale = 255), [
+        Color::new(0, 0, 0, 255),
+        Color::new(127, 127, 127, 255),
+        Color::new(255, 255, 255, 255),
+    ]);
+    assert_eq!(gradient!(Color::new; Black, White; steps = 3, space = linear, round = half_up), [
+        Color::new(0, 0, 0, 255),
+        Color::new(188, 188, 188, 255),
+        Color::new(255, 255, 255, 255),
+    ]);
+    const LUT: [Color; 5] = gradient!(Color => {r, g, b, a}; Red, Red 50%, Blue; steps = 5, space = srgb, round = trunc, scale = 255);
+    assert_eq!(LUT[1], Color::new(255, 0, 0, 255));
+    assert_eq!(LUT[3], Color::new(127, 0, 127, 255));
+    assert_eq!(gradient!(Color::new; Sky400 / 25%, Sky400; steps = 2, space = srgb, round = trunc, scale = 255), [
+        rgba!(Color::new, Sky400 / 25%, round = trunc, scale = 255),
+        rgba!(Color::new, Sky400),
+    ]);
+    assert_eq!(gradient!(Color::new; Red / 50%, Red / 50% 50%, Blue; steps = 3, space = srgb, round = trunc, scale = 255), [
+        Color::new(255, 0, 0, 127),
+        Color::new(255, 0, 0, 127),
+        Color::new(0, 0, 255, 255),
+    ]);
+    assert_eq!(gradient!(Color3::new; [255, 0, 0], [0, 0, 255]; steps = 2, output = rgb), [
+        Color3::new(255, 0, 0),
+        Color3::new(0, 0, 255),
+    ]);
+    // Transparent stops do not darken the gradient.
+    assert_eq!(gradient!(Color::new; [255, 0, 0, 0], [255, 0, 0]; steps = 3, space = srgb, round = trunc, scale = 255)[1], Color::new(255, 0, 0, 127));
+    let shorter = gradient!(Color::new; "oklch(0.7 0.1 10)", "oklch(0.7 0.1 350)"; steps = 3, space = oklch);
+    let longer = gradient!(Color::new; "oklch(0.7 0.1 10)", "oklch(0.7 0.1 350)"; steps = 3, space = oklch, hue = longer);
+    assert_eq!(shorter[1], rgba!(Color::new, "oklch(0.7 0.1 0)"));
+    assert_eq!(longer[1], rgba!(Color::new, "oklch(0.7 0.1 180)"));
+}
+
+#[test]
+pub fn test_color_functions(){
+    assert_eq!(rgba!(Color::new, Red / 50, round = trunc, scale = 255), Color::new(255, 0, 0, 127));
+    assert_eq!(rgba!(Color::new, "#AABBCC" / 40%, round = trunc, scale = 255), Color::new(0xAA, 0xBB, 0xCC, 102));
+    assert_eq!(rgba!(Color::new, with_alpha("#AABBCC", 0.4), round = trunc, scale = 255), Color::new(0xAA, 0xBB, 0xCC, 102));
+    assert_eq!(rgba!(Color::new, with_alpha(Red, 100%)), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, mix(Red, Blue, 0%)), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, mix(Red, Blue, 100%)), Color::new(0, 0, 255, 255));
+    assert_eq!(rgba!(Color::new, mix(Red, Blue)), rgba!(Color::new, mix(Blue, Red)));
+    assert_eq!(rgba!(Color::new, mix(Red, Blue, 30%)), gradient!(Color::new; Red, Blue 100%; steps = 11)[3]);
+    assert_eq!(rgba!(Color::new, lighten(Black, 100%)), Color::new(255, 255, 255, 255));
+    assert_eq!(rgba!(Color::new, darken(White, 100%)), Color::new(0, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, desaturate(Red, 100%)), rgba!(Color::new, "oklch(0.627955 0 0)"));
+    assert_eq!(rgba!(Color::new, mix(Red, Blue / 0), round = trunc, scale = 255), Color::new(255, 0, 0, 127));
+    assert_eq!(rgba_u32!(with_alpha(Red, 0.0)), 0xFF000000);
+}
+
+#[test]
+pub fn test_css_color5(){
+    assert_eq!(rgba!(Color::new, "color-mix(in srgb, red, blue)", round = trunc, scale = 255), Color::new(127, 0, 127, 255));
+    assert_eq!(rgba!(Color::new, "color-mix(in srgb, red 100%, blue)"), Color::new(255, 0, 0, 255));
+    assert_eq!(rgba!(Color::new, "color-mix(in srgb, #FF0000 25%, blue 25%)", round = trunc, scale = 255), Color::new(127, 0, 127, 127));
+    assert_eq!(rgba!(Color::new, "color-mix(in oklab, red 40%, blue)"), rgba!(Color::new, mix(Red, Blue, 60%)));
+    assert_eq!(
+        rgba!(Color::new, "color-mix(in oklch longer hue, oklch(0.7 0.1 10), oklch(0.7 0.1 350))"),
+        rgba!(Color::new, "oklch(0.7 0.1 180)"),
+    );
+    assert_eq!(rgba!(Color::new, "rgb(from #336699 r g calc(b * 0.5))", round = trunc, scale = 255), Color::new(0x33, 0x66, 0x4C, 255));
+    assert_eq!(rgba!(Color::new, "rgb(from #336699 calc((r + g) / 2) 0 b / 50%)", round = trunc, scale = 255), Color::new(0x4C, 0, 0x99, 127));
+    assert_eq!(rgba!(Color::new, "rgb(from rgb(10 20 30) b g r / alpha)"), Color::new(30, 20, 10, 255));
+    assert_eq!(rgba!(Color::new, "hsl(from red calc(h + 120) s l)"), Color::new(0, 255, 0, 255));
+    assert_eq!(rgba!(Color::new, "oklch(from Sky400 l c h)", round = trunc, scale = 255), rgba!(Color::new, Sky400, round = trunc, scale = 255));
+    assert_eq!(rgba!(Color::new, "hwb(from white h w calc(b + 100))", round = trunc, scale = 255), Color::new(127, 127, 127, 255));
+}
+
+#[test]
+pub fn test_runtime_parse(){
+    macro_rules! same {
+        ($($tt:tt)*) => {
+            // `to_u8` truncates like the default rounding.
+            let [r, g, b, a] = colorthis_core::parse(stringify!($($tt)*)).unwrap().to_u8();
+            assert_eq!(Color::new(r, g, b, a), rgba!(Color::new, $($tt)*, round = trunc, scale = 255));
+        };
+    }
+    same!("#AABBCC");
+    same!(0x11223344);
+    same!([0.3; 3, 0.8]);
+    same!([12, 34, 56]);
+    same!(Sky400 / 50);
+    same!(hsl[210deg, 0.4, 0.5]);
+    same!(oklch[0.7, 0.1, 200]);
+    same!(mix(Red, Blue, 30%));
+    same!(lighten(Sky400, 10%));
+    same!("color-mix(in oklch, red, blue)");
+    same!("rgb(from #336699 r g calc(b * 0.5) / 50%)");
+}
+
+#[test]
+pub fn test_derive(){
+    #[derive(Debug, PartialEq, ColorThis)]
+    struct Rgb8 {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+    assert_eq!(rgb8!(Red), Rgb8 { r: 255, g: 0, b: 0 });
+    assert_eq!(rgb8!(1, 2, 3), Rgb8 { r: 1, g: 2, b: 3 });
+    assert_eq!(rgb8!([0.2; 3]), Rgb8 { r: 51, g: 51, b: 51 });
+
+    #[derive(Debug, PartialEq, ColorThis)]
+    #[colorthis(macro = pixel)]
+    struct Pixel(u16, u16, u16, u16);
+    assert_eq!(pixel!([1.0, 0.0, 0.0, 0.0]), Pixel(65535, 0, 0, 0));
+
+    #[derive(Debug, PartialEq, Default, ColorThis)]
+    struct Vertex {
+        position: [f32; 2],
+        alpha: f32,
+        blue: f32,
+        green: f32,
+        red: f32,
+    }
+    assert_eq!(vertex!(Blue / 50), Vertex { position: [0.0; 2], red: 0.0, green: 0.0, blue: 1.0, alpha: 0.5 });
+    assert_eq!(vertex!(0.5, 0.25, 0.0), Vertex { position: [0.0; 2], red: 0.5, green: 0.25, blue: 0.0, alpha: 1.0 });
+
+    #[derive(Debug, PartialEq, ColorThis)]
+    #[allow(dead_code)]
+    enum Paint {
+        #[colorthis(premul)]
+        Premul { r: u8, g: u8, b: u8, a: u8 },
+        #[colorthis(macro = signed)]
+        Signed(i8, i8, i8),
+        Unmarked([u8; 3]),
+    }
+    #[cfg(not(feature="scale-256"))]
+    assert_eq!(premul!([255, 255, 255, 128]), Paint::Premul { r: 128, g: 128, b: 128, a: 128 });
+    assert_eq!(premul!([255, 128, 0, 0]), Paint::Premul { r: 0, g: 0, b: 0, a: 0 });
+    assert_eq!(signed!(White), Paint::Signed(127, 127, 127));
+}
+
+mod gray {
+    /// Implemented by hand, `color!` works without importing the trait.
+    #[derive(Debug, PartialEq)]
+    pub struct Gray(pub u8);
+
+    impl colorthis_core::FromColorThis for Gray {
+        fn from_color_this(color: colorthis_core::Channels) -> Self {
+            let [r, g, b, _] = color.u8;
+            Gray(((r as u16 + g as u16 + b as u16) / 3) as u8)
+        }
+    }
+}
+
+#[test]
+pub fn test_from_color_this_manual(){
+    assert_eq!(color!(gray::Gray, Red), gray::Gray(85));
+    assert_eq!(color!(gray::Gray, [30, 60, 90]), gray::Gray(60));
+}
+
+#[test]
+pub fn test_from_color_this(){
+    use colorthis_core::{FromColorThis, Rgba};
+
+    #[derive(Debug, PartialEq, FromColorThis)]
+    struct Srgb8 {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+    const ORANGE: Srgb8 = color!(Srgb8, "#FF8800");
+    assert_eq!(ORANGE, Srgb8 { r: 255, g: 136, b: 0 });
+    assert_eq!(color!(Srgb8, [0.5; 3], round = half_up), Srgb8 { r: 128, g: 128, b: 128 });
+
+    #[derive(Debug, PartialEq, FromColorThis)]
+    struct Deep(u16, u16, u16, u16);
+    assert_eq!(color!(Deep, "#FF000080", round = trunc, scale = 255), Deep(65535, 0, 0, 32896));
+
+    #[derive(Debug, PartialEq, Default, FromColorThis)]
+    #[colorthis(linear)]
+    struct Linear {
+        position: [f64; 2],
+        red: f64,
+        green: f64,
+        blue: f64,
+        alpha: f64,
+    }
+    assert_eq!(color!(Linear, White / 50), Linear { position: [0.0; 2], red: 1.0, green: 1.0, blue: 1.0, alpha: 0.5 });
+
+    #[derive(Debug, PartialEq, FromColorThis)]
+    #[allow(dead_code)]
+    enum Paint {
+        Solid(u8, u8, u8),
+        #[colorthis(premul)]
+        Premul { r: f32, g: f32, b: f32, a: f32 },
+    }
+    assert_eq!(color!(Paint, [1.0, 0.5, 0.0, 0.5]), Paint::Premul { r: 0.5, g: 0.25, b: 0.0, a: 0.5 });
+
+    fn generic<T: FromColorThis>() -> T {
+        color!(T, Blue)
+    }
+    assert_eq!(generic::<Rgba>(), Rgba::new(0.0, 0.0, 1.0, 1.0));
+    assert_eq!(generic::<Srgb8>(), Srgb8 { r: 0, g: 0, b: 255 });
+}